@@ -0,0 +1,103 @@
+use quote::ToTokens;
+use syn::spanned::Spanned;
+use syn::{Attribute, FnArg, ImplItem, ItemImpl, LitStr, Meta, Path, Type};
+
+use crate::routes::{Route, Routes, STANDARD_METHODS};
+
+/// `#[controller(name => ..., ...)]` on an `impl` block whose associated functions carry
+/// `#[get("/...")]`-style attributes. The first argument of each function is its path struct.
+pub struct Controller {
+    item: ItemImpl,
+    routes: Routes,
+}
+
+impl Controller {
+    pub fn new(mut routes: Routes, mut item: ItemImpl) -> syn::Result<Self> {
+        if !item.generics.params.is_empty() {
+            return Err(syn::Error::new_spanned(
+                &item.generics,
+                "generic controllers are not supported",
+            ));
+        }
+        let self_path = match &*item.self_ty {
+            Type::Path(ty) if ty.qself.is_none() => ty.path.clone(),
+            ty => return Err(syn::Error::new_spanned(ty, "expected a type path")),
+        };
+        let mut entries: Vec<Route> = Vec::new();
+        for impl_item in &mut item.items {
+            let ImplItem::Fn(function) = impl_item else {
+                continue;
+            };
+            let (route_attrs, attrs): (Vec<Attribute>, Vec<Attribute>) =
+                std::mem::take(&mut function.attrs)
+                    .into_iter()
+                    .partition(is_route_attr);
+            function.attrs = attrs;
+            if route_attrs.is_empty() {
+                continue;
+            }
+            if let Some(receiver) = function.sig.receiver() {
+                return Err(syn::Error::new_spanned(
+                    receiver,
+                    "handlers can't take `self`",
+                ));
+            }
+            let path_struct = match function.sig.inputs.first() {
+                Some(FnArg::Typed(arg)) => match &*arg.ty {
+                    Type::Path(ty) if ty.qself.is_none() => ty.path.clone(),
+                    ty => return Err(syn::Error::new_spanned(ty, "expected a path struct")),
+                },
+                _ => {
+                    return Err(syn::Error::new(
+                        function.sig.span(),
+                        "expected the path struct as the first argument",
+                    ))
+                }
+            };
+            let mut handler: Path = self_path.clone();
+            handler.segments.push(function.sig.ident.clone().into());
+            for attr in route_attrs {
+                let method = attr.path().require_ident()?.clone();
+                let path = match &attr.meta {
+                    Meta::Path(_) => None,
+                    _ => Some(attr.parse_args::<LitStr>()?),
+                };
+                let key = path_struct.to_token_stream().to_string();
+                let existing = entries
+                    .iter_mut()
+                    .find(|route| route.path_struct().to_token_stream().to_string() == key);
+                match existing {
+                    Some(route) => {
+                        if route.path().map(LitStr::value) != path.as_ref().map(LitStr::value) {
+                            return Err(syn::Error::new_spanned(
+                                attr,
+                                format!("`{key}` is already routed at a different path"),
+                            ));
+                        }
+                        route.add_handler(method, handler.clone(), &function.attrs)?;
+                    }
+                    None => {
+                        let mut route = Route::new(path, path_struct.clone())?;
+                        route.add_handler(method, handler.clone(), &function.attrs)?;
+                        entries.push(route);
+                    }
+                }
+            }
+        }
+        routes.set_routes(entries);
+        Ok(Controller { item, routes })
+    }
+}
+
+fn is_route_attr(attr: &Attribute) -> bool {
+    attr.path().get_ident().is_some_and(|ident| {
+        ident == "any" || STANDARD_METHODS.contains(&ident.to_string().as_str())
+    })
+}
+
+impl ToTokens for Controller {
+    fn to_tokens(&self, tokens: &mut proc_macro2::TokenStream) {
+        self.item.to_tokens(tokens);
+        self.routes.to_tokens(tokens);
+    }
+}
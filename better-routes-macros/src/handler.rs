@@ -0,0 +1,134 @@
+use quote::{quote_spanned, ToTokens};
+use syn::parse::{Parse, ParseStream};
+use syn::punctuated::Punctuated;
+use syn::spanned::Spanned;
+use syn::{FnArg, Ident, ItemFn, Path, Token, Type};
+
+use crate::routes::{cfgs, STANDARD_METHODS};
+
+/// The `Table, get, ...` arguments of `#[handler]`.
+pub struct HandlerArgs {
+    table: Path,
+    methods: Vec<Ident>,
+}
+
+impl Parse for HandlerArgs {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let table = input.parse()?;
+        input.parse::<Token![,]>()?;
+        let methods = Punctuated::<Ident, Token![,]>::parse_terminated(input)?;
+        if methods.is_empty() {
+            return Err(syn::Error::new(
+                input.span(),
+                "expected at least one method",
+            ));
+        }
+        let mut seen: Vec<&Ident> = Vec::new();
+        for method in &methods {
+            if method == "connect" {
+                return Err(syn::Error::new(
+                    method.span(),
+                    "connect handlers must be declared in `routes!`",
+                ));
+            }
+            if !STANDARD_METHODS.contains(&method.to_string().as_str()) {
+                return Err(syn::Error::new(
+                    method.span(),
+                    format!(
+                        "expected one of {}",
+                        STANDARD_METHODS
+                            .iter()
+                            .filter(|method| **method != "connect")
+                            .map(|method| format!("`{method}`"))
+                            .collect::<Vec<_>>()
+                            .join(", ")
+                    ),
+                ));
+            }
+            if seen.contains(&method) {
+                return Err(syn::Error::new(
+                    method.span(),
+                    format!("duplicate {method} method"),
+                ));
+            }
+            seen.push(method);
+        }
+        Ok(HandlerArgs {
+            table,
+            methods: methods.into_iter().collect(),
+        })
+    }
+}
+
+/// `#[handler(Table, get, ...)]` on a handler function, registering it with the route table
+/// generated by `routes!`. Its first argument is its path struct.
+pub struct Handler {
+    args: HandlerArgs,
+    item: ItemFn,
+    path_struct: Path,
+}
+
+impl Handler {
+    pub fn new(args: HandlerArgs, item: ItemFn) -> syn::Result<Self> {
+        if !item.sig.generics.params.is_empty() {
+            return Err(syn::Error::new_spanned(
+                &item.sig.generics,
+                "generic handlers are not supported",
+            ));
+        }
+        let path_struct = match item.sig.inputs.first() {
+            Some(FnArg::Typed(arg)) => match &*arg.ty {
+                Type::Path(ty) if ty.qself.is_none() => ty.path.clone(),
+                ty => return Err(syn::Error::new_spanned(ty, "expected a path struct")),
+            },
+            _ => {
+                return Err(syn::Error::new(
+                    item.sig.span(),
+                    "expected the path struct as the first argument",
+                ))
+            }
+        };
+        Ok(Handler {
+            args,
+            item,
+            path_struct,
+        })
+    }
+}
+
+impl ToTokens for Handler {
+    fn to_tokens(&self, tokens: &mut proc_macro2::TokenStream) {
+        let Handler {
+            args: HandlerArgs { table, methods },
+            item,
+            path_struct,
+        } = self;
+        let handler = &item.sig.ident;
+        let cfgs = cfgs(&item.attrs);
+        item.to_tokens(tokens);
+        for method in methods {
+            let cfgs = cfgs.clone();
+            let name = method.to_string().to_uppercase();
+            let filter = Ident::new(&name, method.span());
+            tokens.extend(quote_spanned! {
+                method.span() =>
+                #(#cfgs)*
+                ::better_routes::__private::inventory::submit! {
+                    ::better_routes::__private::Registration {
+                        table: ::std::any::TypeId::of::<#table>,
+                        path: <#path_struct as ::axum_extra::routing::TypedPath>::PATH,
+                        method: #name,
+                        register: |method_router| {
+                            ::better_routes::__private::register::<#table, _, _, _, #path_struct>(
+                                method_router,
+                                ::axum::routing::MethodFilter::#filter,
+                                #handler,
+                            )
+                        },
+                        location: ::std::concat!(::std::file!(), ":", ::std::line!()),
+                    }
+                }
+            });
+        }
+    }
+}
@@ -1,9 +1,15 @@
+mod controller;
+mod handler;
+mod path;
 mod routes;
 
 use proc_macro::TokenStream;
 use quote::ToTokens;
-use syn::parse_macro_input;
+use syn::{parse_macro_input, ItemFn, ItemImpl};
 
+use crate::controller::Controller;
+use crate::handler::{Handler, HandlerArgs};
+use crate::path::DerivePath;
 use crate::routes::Routes;
 
 #[proc_macro]
@@ -12,3 +18,30 @@ pub fn routes(input: TokenStream) -> TokenStream {
         .into_token_stream()
         .into()
 }
+
+#[proc_macro_derive(Path, attributes(typed_path))]
+pub fn derive_path(input: TokenStream) -> TokenStream {
+    parse_macro_input!(input as DerivePath)
+        .into_token_stream()
+        .into()
+}
+
+#[proc_macro_attribute]
+pub fn controller(args: TokenStream, input: TokenStream) -> TokenStream {
+    let routes = parse_macro_input!(args with Routes::parse_options);
+    let item = parse_macro_input!(input as ItemImpl);
+    Controller::new(routes, item)
+        .map(ToTokens::into_token_stream)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+#[proc_macro_attribute]
+pub fn handler(args: TokenStream, input: TokenStream) -> TokenStream {
+    let args = parse_macro_input!(args as HandlerArgs);
+    let item = parse_macro_input!(input as ItemFn);
+    Handler::new(args, item)
+        .map(ToTokens::into_token_stream)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
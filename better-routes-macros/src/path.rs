@@ -0,0 +1,90 @@
+use proc_macro2::Ident;
+use quote::ToTokens;
+use syn::parse::{Parse, ParseStream};
+use syn::{Data, DeriveInput, Fields, Generics, LitStr, Path, Token};
+
+use crate::routes::{parse_path, path_impls, Segment};
+
+mod kw {
+    syn::custom_keyword!(rejection);
+}
+
+/// `#[derive(Path)]` on a struct carrying a `#[typed_path("/...")]` attribute.
+pub struct DerivePath {
+    ident: Ident,
+    generics: Generics,
+    tuple: bool,
+    path: LitStr,
+    segments: Vec<Segment>,
+    rejection: Option<Path>,
+}
+
+impl Parse for DerivePath {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let DeriveInput {
+            attrs,
+            ident,
+            generics,
+            data,
+            ..
+        } = input.parse()?;
+        let Data::Struct(data) = data else {
+            return Err(syn::Error::new(
+                ident.span(),
+                "Path can only be derived for structs",
+            ));
+        };
+        let tuple = matches!(data.fields, Fields::Unnamed(_));
+        let attr = attrs
+            .iter()
+            .find(|attr| attr.path().is_ident("typed_path"))
+            .ok_or_else(|| {
+                syn::Error::new(ident.span(), "missing `#[typed_path(\"/...\")]` attribute")
+            })?;
+        let (path, rejection) = attr.parse_args_with(|input: ParseStream| {
+            let path: LitStr = input.parse()?;
+            let mut rejection = None;
+            if !input.is_empty() {
+                input.parse::<Token![,]>()?;
+                input.parse::<kw::rejection>()?;
+                input.parse::<Token![=]>()?;
+                rejection = Some(input.parse()?);
+                input.parse::<Option<Token![,]>>()?;
+            }
+            Ok((path, rejection))
+        })?;
+        let (path, segments) = parse_path(&path)?;
+        Ok(DerivePath {
+            ident,
+            generics,
+            tuple,
+            path,
+            segments,
+            rejection,
+        })
+    }
+}
+
+impl ToTokens for DerivePath {
+    fn to_tokens(&self, tokens: &mut proc_macro2::TokenStream) {
+        let DerivePath {
+            ident,
+            generics,
+            tuple,
+            path,
+            segments,
+            rejection,
+        } = self;
+        tokens.extend(path_impls(
+            path,
+            segments,
+            None,
+            ident,
+            generics,
+            *tuple,
+            !generics.params.is_empty(),
+            rejection,
+            &[],
+        ));
+    }
+}
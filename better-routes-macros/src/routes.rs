@@ -1,360 +1,2683 @@
-use proc_macro2::Span;
+use proc_macro2::{Delimiter, Span};
 use quote::{format_ident, quote, quote_spanned, ToTokens};
 use syn::parse::{Parse, ParseStream};
 use syn::spanned::Spanned;
-use syn::{braced, Ident, LitStr, Path, Token, Visibility};
+use syn::{
+    braced, bracketed, parenthesized, parse_quote, parse_quote_spanned, Attribute, Expr, ExprLit,
+    ExprRange, Field, Fields, FieldsNamed, FieldsUnnamed, GenericParam, Generics, Ident,
+    ItemStruct, Lit, LitInt, LitStr, Path, Token, Type, Visibility,
+};
 
 mod kw {
     syn::custom_keyword!(name);
     syn::custom_keyword!(state);
     syn::custom_keyword!(rejection);
-    syn::custom_keyword!(get);
-    syn::custom_keyword!(post);
-    syn::custom_keyword!(put);
-    syn::custom_keyword!(patch);
-    syn::custom_keyword!(delete);
+    syn::custom_keyword!(method);
+    syn::custom_keyword!(any);
+    syn::custom_keyword!(method_not_allowed);
+    syn::custom_keyword!(fallback);
+    syn::custom_keyword!(fallback_service);
+    syn::custom_keyword!(group);
+    syn::custom_keyword!(layer);
+    syn::custom_keyword!(route_layer);
+    syn::custom_keyword!(merge);
+    syn::custom_keyword!(nest);
+    syn::custom_keyword!(trailing_slash);
+    syn::custom_keyword!(redirect);
+    syn::custom_keyword!(text);
+    syn::custom_keyword!(status);
+    syn::custom_keyword!(host);
+    syn::custom_keyword!(not_acceptable);
+    syn::custom_keyword!(require_header);
+    syn::custom_keyword!(htmx_only);
+    syn::custom_keyword!(guard);
+    syn::custom_keyword!(accept);
+    syn::custom_keyword!(content_type);
 }
 
-struct Route {
+/// Methods that can be used in a route block without `method "NAME"`.
+pub(crate) const STANDARD_METHODS: [&str; 9] = [
+    "get", "head", "post", "put", "patch", "delete", "options", "trace", "connect",
+];
+
+/// An entry of `routes!` or of a group, with its `#[cfg]`, `#[doc]` and `#[deprecated]`
+/// attributes.
+struct Entry {
+    attrs: Vec<Attribute>,
+    kind: EntryKind,
+}
+
+enum EntryKind {
+    Route(Box<Route>),
+    Group(Group),
+    Host(HostBlock),
+    /// `merge => OtherRoutes`
+    Merge(Path),
+    /// `"/prefix" => nest OtherRoutes`
+    Nest {
+        prefix: LitStr,
+        routes: Path,
+    },
+    /// `"/path" => redirect Target`, `"/path" => text "..."` or `"/path" => status 204`
+    Response {
+        path: LitStr,
+        response: StaticResponse,
+    },
+}
+
+/// The response of an entry without a handler, served for every method.
+enum StaticResponse {
+    /// A `308 Permanent Redirect` to the `Display` of a path struct.
+    Redirect(Expr),
+    /// A `200 OK` with a `text/plain` body.
+    Text(LitStr),
+    Status(LitInt),
+}
+
+impl Parse for StaticResponse {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        if input.peek(kw::redirect) {
+            input.parse::<kw::redirect>()?;
+            Ok(StaticResponse::Redirect(input.parse()?))
+        } else if input.peek(kw::text) {
+            input.parse::<kw::text>()?;
+            Ok(StaticResponse::Text(input.parse()?))
+        } else {
+            input.parse::<kw::status>()?;
+            let status: LitInt = input.parse()?;
+            if !matches!(status.base10_parse::<u16>(), Ok(100..=999)) {
+                return Err(syn::Error::new_spanned(
+                    status,
+                    "status codes must be between 100 and 999",
+                ));
+            }
+            Ok(StaticResponse::Status(status))
+        }
+    }
+}
+
+impl ToTokens for StaticResponse {
+    fn to_tokens(&self, tokens: &mut proc_macro2::TokenStream) {
+        tokens.extend(match self {
+            StaticResponse::Redirect(target) => quote_spanned! {
+                target.span() => ::better_routes::__private::redirect_to(#target)
+            },
+            StaticResponse::Text(text) => quote! { ::better_routes::__private::text(#text) },
+            StaticResponse::Status(status) => {
+                quote! { ::better_routes::__private::status(#status) }
+            }
+        });
+    }
+}
+
+struct Group {
+    prefix: LitStr,
+    /// Whether the group is inside a `host` block.
+    hosted: bool,
+    state: Option<Path>,
+    layers: Vec<Layer>,
+    requirements: Vec<Requirement>,
+    entries: Vec<Entry>,
+}
+
+/// `host "api.example.com" => { ... }`, whose routes are only served to requests for that host.
+struct HostBlock {
+    host: Host,
+    state: Option<Path>,
+    layers: Vec<Layer>,
+    requirements: Vec<Requirement>,
+    entries: Vec<Entry>,
+}
+
+/// The host of a `host` block, whose labels can be captures: `":tenant.example.com"`.
+#[derive(Clone)]
+pub(crate) struct Host {
+    /// The host with its captures written `:name`, as matched at runtime.
+    pattern: LitStr,
+    /// The labels of the host.
+    segments: Vec<Segment>,
+}
+
+impl Host {
+    fn parse(host: LitStr) -> syn::Result<Self> {
+        let value = host.value();
+        if value.contains(['/', '*', '<']) {
+            return Err(syn::Error::new_spanned(
+                host,
+                "hosts can't contain `/`, wildcards or constraints",
+            ));
+        }
+        // Parsed as a path, with a segment for each label.
+        let path = LitStr::new(&format!("/{}", value.replace('.', "/")), host.span());
+        let (path, mut segments) = parse_path(&path)?;
+        segments.remove(0);
+        let valid = |label: &str| {
+            !label.is_empty() && label.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+        };
+        for segment in &segments {
+            if let Segment::Static(label) = segment {
+                if !valid(label) {
+                    return Err(syn::Error::new_spanned(
+                        host,
+                        "host labels must be letters, digits and `-`, or captures",
+                    ));
+                }
+            }
+        }
+        let pattern = path.value()[1..].replace('/', ".");
+        Ok(Host {
+            pattern: LitStr::new(&pattern, host.span()),
+            segments,
+        })
+    }
+
+    fn has_captures(&self) -> bool {
+        self.segments
+            .iter()
+            .any(|segment| matches!(segment, Segment::Capture(_)))
+    }
+}
+
+/// Where entries are parsed: under the prefix of their groups, in a `host` block or not, and
+/// in a block with requirements or not.
+#[derive(Clone, Copy, Default)]
+struct Scope<'a> {
+    prefix: Option<&'a LitStr>,
+    host: Option<&'a Host>,
+    has_requirements: bool,
+}
+
+/// An option of a route or a block that requests must satisfy to be served by its routes,
+/// checked before the path struct is extracted.
+enum Requirement {
+    /// `require_header "X-Name"` or `htmx_only`
+    Header(RequiredHeader),
+    /// `guard => path`, falling through to the fallback when the guard fails.
+    Guard(Path),
+}
+
+impl Parse for Requirement {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        if input.peek(kw::guard) {
+            input.parse::<kw::guard>()?;
+            input.parse::<Token![=>]>()?;
+            Ok(Requirement::Guard(input.parse()?))
+        } else {
+            Ok(Requirement::Header(input.parse()?))
+        }
+    }
+}
+
+impl Requirement {
+    fn peek(input: ParseStream) -> bool {
+        input.peek(kw::require_header) || input.peek(kw::htmx_only) || input.peek(kw::guard)
+    }
+}
+
+/// `require_header "X-Name"` or `htmx_only`, which is `require_header "HX-Request"`, serving a
+/// route only to requests with the header. Other requests are passed to the `rejection`
+/// handler, if any, before the path struct is extracted.
+struct RequiredHeader {
+    /// The lowercase name of the header.
+    name: LitStr,
+    rejection: Option<Path>,
+}
+
+impl Parse for RequiredHeader {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let name = if input.peek(kw::htmx_only) {
+            let keyword = input.parse::<kw::htmx_only>()?;
+            LitStr::new("hx-request", keyword.span)
+        } else {
+            input.parse::<kw::require_header>()?;
+            let lit: LitStr = input.parse()?;
+            let value = lit.value();
+            if value.is_empty() || !value.chars().all(is_token_char) {
+                return Err(syn::Error::new_spanned(
+                    lit,
+                    "header names must be a non-empty HTTP token",
+                ));
+            }
+            LitStr::new(&value.to_ascii_lowercase(), lit.span())
+        };
+        let rejection = if input.peek(Token![=>]) {
+            input.parse::<Token![=>]>()?;
+            Some(input.parse()?)
+        } else {
+            None
+        };
+        Ok(RequiredHeader { name, rejection })
+    }
+}
+
+/// Adds `requirement` to `requirements`, rejecting headers that are already required.
+fn push_requirement(
+    requirements: &mut Vec<Requirement>,
+    requirement: Requirement,
+) -> syn::Result<()> {
+    if let Requirement::Header(header) = &requirement {
+        if requirements.iter().any(|other| match other {
+            Requirement::Header(other) => other.name.value() == header.name.value(),
+            Requirement::Guard(_) => false,
+        }) {
+            return Err(syn::Error::new(
+                header.name.span(),
+                format!("`{}` is already required", header.name.value()),
+            ));
+        }
+    }
+    requirements.push(requirement);
+    Ok(())
+}
+
+/// A `layer => expr` or `route_layer => expr` option, applied in the order they are declared.
+struct Layer {
+    route_only: bool,
+    layer: Expr,
+}
+
+/// Another path of a route, `"/a" | "/b"`, serving its handlers or, with `redirect`,
+/// redirecting to its first path.
+struct Alias {
     path: LitStr,
+    redirect: bool,
+}
+
+pub(crate) struct Route {
+    /// `None` for path structs deriving `Path`, which implement `TypedPath` themselves.
+    path: Option<LitStr>,
     segments: Vec<Segment>,
     path_struct: Path,
+    /// The parameters of a generic path struct, declared as on an impl: `Page<K: Kind>`.
+    generics: Generics,
+    /// Whether the path struct is a tuple struct, declared as `UserPath(..)`.
+    tuple: bool,
+    /// The path struct defined inline, as `UserPath { id: u64 }`, without its attributes.
+    definition: Option<ItemStruct>,
+    aliases: Vec<Alias>,
+    /// The host of the `host` block of the route, whose captures are fields of the path struct.
+    host: Option<Host>,
     rejection: Option<Path>,
-    get_handler: Option<Path>,
-    post_handler: Option<Path>,
-    put_handler: Option<Path>,
-    patch_handler: Option<Path>,
-    delete_handler: Option<Path>,
+    endpoint: Endpoint,
+}
+
+#[derive(Default)]
+struct Endpoint {
+    handlers: Vec<MethodHandler>,
+    any_handler: Option<Path>,
+    method_not_allowed: Option<Path>,
+    not_acceptable: Option<Path>,
+    state: Option<Path>,
+    layers: Vec<Layer>,
+    trailing_slash: Option<TrailingSlash>,
+    requirements: Vec<Requirement>,
+}
+
+/// How a route serves its path with or without a trailing slash, the other way than it is
+/// declared.
+#[derive(Clone, Copy)]
+enum TrailingSlash {
+    Strict,
+    Redirect,
+    Both,
+}
+
+impl Parse for TrailingSlash {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let ident: Ident = input.parse()?;
+        match ident.to_string().as_str() {
+            "strict" => Ok(TrailingSlash::Strict),
+            "redirect" => Ok(TrailingSlash::Redirect),
+            "both" => Ok(TrailingSlash::Both),
+            _ => Err(syn::Error::new(
+                ident.span(),
+                "expected `strict`, `redirect` or `both`",
+            )),
+        }
+    }
+}
+
+impl ToTokens for TrailingSlash {
+    fn to_tokens(&self, tokens: &mut proc_macro2::TokenStream) {
+        let variant = match self {
+            TrailingSlash::Strict => quote! { Strict },
+            TrailingSlash::Redirect => quote! { Redirect },
+            TrailingSlash::Both => quote! { Both },
+        };
+        tokens.extend(quote! { ::better_routes::__private::TrailingSlash::#variant });
+    }
+}
+
+struct MethodHandler {
+    attrs: Vec<Attribute>,
+    methods: Vec<Method>,
+    /// The media type of the `Content-Type` the handler is chosen for among the handlers of its
+    /// methods.
+    content_type: Option<MediaType>,
+    /// The media type of the `Accept` header the handler is chosen for among the handlers of
+    /// its methods with the same `content_type`.
+    accept: Option<MediaType>,
+    /// Passes requests to the next handler of the same methods and media types when it fails.
+    guard: Option<Path>,
+    handler: Path,
+}
+
+/// A media type in `get(json) => ...` or `get(accept => json) => ...`, matched against the
+/// `Accept` header of the request, or in `post(form) => ...` or
+/// `post(content_type => form) => ...`, against its `Content-Type`.
+struct MediaType {
+    value: String,
+}
+
+impl PartialEq for MediaType {
+    fn eq(&self, other: &Self) -> bool {
+        self.value == other.value
+    }
+}
+
+impl Parse for MediaType {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        if input.peek(LitStr) {
+            let lit: LitStr = input.parse()?;
+            let value = lit.value().to_ascii_lowercase();
+            let valid = |part: &str| !part.is_empty() && part.chars().all(is_token_char);
+            return match value.split_once('/') {
+                Some((ty, subtype)) if valid(ty) && valid(subtype) && ty != "*" => {
+                    Ok(MediaType { value })
+                }
+                _ => Err(syn::Error::new_spanned(
+                    lit,
+                    "expected a media type without parameters, like \"application/ld+json\"",
+                )),
+            };
+        }
+        let ident: Ident = input.parse()?;
+        let value = match ident.to_string().as_str() {
+            "form" => "application/x-www-form-urlencoded",
+            "html" => "text/html",
+            "json" => "application/json",
+            "multipart" => "multipart/form-data",
+            "text" => "text/plain",
+            "xml" => "application/xml",
+            _ => {
+                return Err(syn::Error::new(
+                    ident.span(),
+                    "expected `form`, `html`, `json`, `multipart`, `text`, `xml` or a media \
+                     type, like \"application/ld+json\"",
+                ))
+            }
+        };
+        Ok(MediaType {
+            value: value.to_owned(),
+        })
+    }
+}
+
+enum Method {
+    Standard(Ident),
+    Extension(LitStr),
+}
+
+impl Method {
+    fn name(&self) -> String {
+        match self {
+            Method::Standard(ident) => ident.to_string().to_uppercase(),
+            Method::Extension(lit) => lit.value(),
+        }
+    }
+
+    /// The `MethodFilter` constant serving this method, if axum has a usable one.
+    ///
+    /// axum 0.7 stores `MethodFilter::CONNECT` endpoints in its `OPTIONS` slot, so `CONNECT`
+    /// is served by the method fallback like extension methods are.
+    fn filter(&self) -> Option<Ident> {
+        match self {
+            Method::Standard(ident) if ident != "connect" => {
+                Some(format_ident!("{}", self.name(), span = ident.span()))
+            }
+            _ => None,
+        }
+    }
+
+    /// Whether a bare media type on this method is matched against the body it takes rather
+    /// than the response the request accepts.
+    fn takes_body(&self) -> bool {
+        matches!(self, Method::Standard(ident) if ident == "post" || ident == "put" || ident == "patch")
+    }
+
+    fn span(&self) -> Span {
+        match self {
+            Method::Standard(ident) => ident.span(),
+            Method::Extension(lit) => lit.span(),
+        }
+    }
+}
+
+impl Parse for Method {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        if input.peek(kw::method) {
+            input.parse::<kw::method>()?;
+            let lit: LitStr = input.parse()?;
+            let value = lit.value();
+            if value.is_empty() || !value.chars().all(is_token_char) {
+                return Err(syn::Error::new_spanned(
+                    lit,
+                    "extension methods must be a non-empty HTTP token",
+                ));
+            }
+            if STANDARD_METHODS.contains(&value.to_lowercase().as_str()) {
+                return Err(syn::Error::new_spanned(
+                    &lit,
+                    format!("use `{}` instead", value.to_lowercase()),
+                ));
+            }
+            return Ok(Method::Extension(lit));
+        }
+        let ident: Ident = input.parse()?;
+        if !STANDARD_METHODS.contains(&ident.to_string().as_str()) {
+            return Err(syn::Error::new(
+                ident.span(),
+                format!(
+                    "expected one of {}, or `method \"NAME\"`",
+                    STANDARD_METHODS
+                        .map(|method| format!("`{method}`"))
+                        .join(", ")
+                ),
+            ));
+        }
+        Ok(Method::Standard(ident))
+    }
+}
+
+/// Adds `handler` to `handlers`, rejecting methods that already have a handler without a
+/// guard for the same media types. Handlers behind `#[cfg]` may share a method with another
+/// one behind a `#[cfg]`, since they can exclude each other.
+fn push_handler(handlers: &mut Vec<MethodHandler>, handler: MethodHandler) -> syn::Result<()> {
+    // Two handlers behind a `#[cfg]` each may never be compiled together, but one that
+    // overlaps an unconditional handler is a conflict as soon as it is enabled.
+    let has_cfg = cfgs(&handler.attrs).next().is_some();
+    for method in &handler.methods {
+        let name = method.name();
+        let others = handlers
+            .iter()
+            .filter(|other| !has_cfg || cfgs(&other.attrs).next().is_none())
+            .filter(|other| other.methods.iter().any(|other| other.name() == name))
+            .collect::<Vec<_>>();
+        if others.iter().any(|other| {
+            other.content_type == handler.content_type
+                && other.accept == handler.accept
+                && other.guard.is_none()
+        }) {
+            let message = match handler.guard {
+                Some(_) => format!(
+                    "{} handler after one without a guard is never called",
+                    name.to_lowercase()
+                ),
+                None => format!("duplicate {} handler", name.to_lowercase()),
+            };
+            return Err(syn::Error::new(method.span(), message));
+        }
+        // Handlers are chosen by `Content-Type` first, then by `Accept` among the ones for
+        // the same content type.
+        let mixed = |option: &str| {
+            syn::Error::new(
+                method.span(),
+                format!(
+                    "{} handlers with `{option}` can't be mixed with one without",
+                    name.to_lowercase()
+                ),
+            )
+        };
+        if others
+            .iter()
+            .any(|other| other.content_type.is_none() != handler.content_type.is_none())
+        {
+            return Err(mixed("content_type"));
+        }
+        if others.iter().any(|other| {
+            other.content_type == handler.content_type
+                && other.accept.is_none() != handler.accept.is_none()
+        }) {
+            return Err(mixed("accept"));
+        }
+    }
+    handlers.push(handler);
+    Ok(())
+}
+
+impl Parse for Endpoint {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let mut handlers: Vec<MethodHandler> = Vec::new();
+        let mut any_handler = None;
+        let mut method_not_allowed: Option<Path> = None;
+        let mut not_acceptable: Option<Path> = None;
+        let mut state: Option<Path> = None;
+        let mut layers = Vec::new();
+        let mut trailing_slash = None;
+        let mut requirements = Vec::new();
+        let mut count = 0;
+        while !input.is_empty() {
+            if count > 0 {
+                input.parse::<Token![,]>()?;
+                if input.is_empty() {
+                    break;
+                }
+            }
+            if input.peek(kw::any) {
+                let any = input.parse::<kw::any>()?;
+                input.parse::<Token![=>]>()?;
+                if any_handler.is_some() {
+                    return Err(syn::Error::new(any.span, "duplicate any handler"));
+                }
+                any_handler = Some(input.parse()?);
+            } else if input.peek(kw::method_not_allowed) {
+                let keyword = input.parse::<kw::method_not_allowed>()?;
+                input.parse::<Token![=>]>()?;
+                if method_not_allowed.is_some() {
+                    return Err(syn::Error::new(
+                        keyword.span,
+                        "duplicate method_not_allowed handler",
+                    ));
+                }
+                method_not_allowed = Some(input.parse()?);
+            } else if input.peek(kw::not_acceptable) {
+                let keyword = input.parse::<kw::not_acceptable>()?;
+                input.parse::<Token![=>]>()?;
+                if not_acceptable.is_some() {
+                    return Err(syn::Error::new(
+                        keyword.span,
+                        "duplicate not_acceptable handler",
+                    ));
+                }
+                not_acceptable = Some(input.parse()?);
+            } else if input.peek(kw::state) {
+                let keyword = input.parse::<kw::state>()?;
+                input.parse::<Token![=>]>()?;
+                if state.is_some() {
+                    return Err(syn::Error::new(keyword.span, "duplicate state option"));
+                }
+                state = Some(input.parse()?);
+            } else if input.peek(kw::trailing_slash) {
+                let keyword = input.parse::<kw::trailing_slash>()?;
+                input.parse::<Token![=>]>()?;
+                if trailing_slash.is_some() {
+                    return Err(syn::Error::new(
+                        keyword.span,
+                        "duplicate trailing_slash option",
+                    ));
+                }
+                trailing_slash = Some(input.parse()?);
+            } else if input.peek(kw::layer) || input.peek(kw::route_layer) {
+                layers.push(input.parse()?);
+            } else if Requirement::peek(input) {
+                push_requirement(&mut requirements, input.parse()?)?;
+            } else {
+                let attrs = parse_attrs(input)?;
+                if let Some(attr) = deprecated(&attrs) {
+                    return Err(syn::Error::new_spanned(
+                        attr,
+                        "`#[deprecated]` can only be put on routes with an inline path struct",
+                    ));
+                }
+                let handler = MethodHandler {
+                    attrs,
+                    ..input.parse()?
+                };
+                push_handler(&mut handlers, handler)?;
+            }
+
+            count += 1;
+        }
+        if let (Some(_), Some(method_not_allowed)) = (&any_handler, &method_not_allowed) {
+            return Err(syn::Error::new(
+                method_not_allowed.span(),
+                "method_not_allowed is never called on a route with an any handler",
+            ));
+        }
+        if let Some(not_acceptable) = &not_acceptable {
+            if !handlers.iter().any(|handler| handler.accept.is_some()) {
+                return Err(syn::Error::new(
+                    not_acceptable.span(),
+                    "not_acceptable is never called on a route without handlers chosen by `Accept`",
+                ));
+            }
+        }
+        Ok(Endpoint {
+            handlers,
+            any_handler,
+            method_not_allowed,
+            not_acceptable,
+            state,
+            layers,
+            trailing_slash,
+            requirements,
+        })
+    }
+}
+
+impl Parse for Layer {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let route_only = if input.peek(kw::route_layer) {
+            input.parse::<kw::route_layer>()?;
+            true
+        } else {
+            input.parse::<kw::layer>()?;
+            false
+        };
+        input.parse::<Token![=>]>()?;
+        let layer = input.parse()?;
+        Ok(Layer { route_only, layer })
+    }
+}
+
+impl Parse for MethodHandler {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let methods = if input.peek(syn::token::Bracket) {
+            let content;
+            let _ = bracketed!(content in input);
+            let methods = content.parse_terminated(Method::parse, Token![,])?;
+            if methods.is_empty() {
+                return Err(syn::Error::new(
+                    content.span(),
+                    "expected at least one method",
+                ));
+            }
+            methods.into_iter().collect()
+        } else {
+            vec![input.parse()?]
+        };
+        let mut content_type = None;
+        let mut accept = None;
+        let mut guard = None;
+        if input.peek(syn::token::Paren) {
+            let content;
+            let _ = parenthesized!(content in input);
+            if content.is_empty() {
+                return Err(syn::Error::new(
+                    content.span(),
+                    "expected a media type, `content_type => ...`, `accept => ...` or \
+                     `guard => ...`",
+                ));
+            }
+            while !content.is_empty() {
+                if content.peek(kw::content_type) {
+                    let keyword = content.parse::<kw::content_type>()?;
+                    parse_option(&content, keyword.span, "content_type", &mut content_type)?;
+                } else if content.peek(kw::accept) {
+                    let keyword = content.parse::<kw::accept>()?;
+                    parse_option(&content, keyword.span, "accept", &mut accept)?;
+                } else if content.peek(kw::guard) {
+                    let keyword = content.parse::<kw::guard>()?;
+                    parse_option(&content, keyword.span, "guard", &mut guard)?;
+                } else if content.peek(Ident) || content.peek(LitStr) {
+                    // A bare media type is short for `content_type => ...` on methods taking a
+                    // body, and for `accept => ...` on the others.
+                    let span = content.span();
+                    let (name, option) = if methods.iter().all(Method::takes_body) {
+                        ("content_type", &mut content_type)
+                    } else if methods.iter().any(Method::takes_body) {
+                        return Err(syn::Error::new(
+                            span,
+                            "a bare media type is ambiguous on methods with and without a body, \
+                             use `content_type => ...` or `accept => ...`",
+                        ));
+                    } else {
+                        ("accept", &mut accept)
+                    };
+                    if option.is_some() {
+                        return Err(syn::Error::new(span, format!("duplicate {name} option")));
+                    }
+                    *option = Some(content.parse()?);
+                    if !content.is_empty() {
+                        content.parse::<Token![,]>()?;
+                    }
+                } else {
+                    return Err(content.error(
+                        "expected a media type, `content_type => ...`, `accept => ...` or \
+                         `guard => ...`",
+                    ));
+                }
+            }
+        }
+        input.parse::<Token![=>]>()?;
+        let handler = input.parse()?;
+        Ok(MethodHandler {
+            attrs: Vec::new(),
+            methods,
+            content_type,
+            accept,
+            guard,
+            handler,
+        })
+    }
+}
+
+/// `tchar` from RFC 9110, the characters allowed in a method name.
+fn is_token_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || "!#$%&'*+-.^_`|~".contains(c)
 }
 
 pub struct Routes {
     state: Option<Path>,
     rejection: Option<Path>,
-    routes: Vec<Route>,
+    method_not_allowed: Option<Path>,
+    not_acceptable: Option<Path>,
+    fallback: Option<Path>,
+    fallback_service: Option<Expr>,
+    layers: Vec<Layer>,
+    trailing_slash: Option<TrailingSlash>,
+    entries: Vec<Entry>,
     name: Ident,
     vis: Visibility,
 }
 
 impl Parse for Routes {
     fn parse(input: ParseStream) -> syn::Result<Self> {
+        let mut routes = Routes::parse_options(input)?;
+        routes.entries = parse_entries(input, Scope::default())?;
+        check_host_paths(&routes.entries)?;
+        Ok(routes)
+    }
+}
+
+impl Routes {
+    /// Parses the `name => ...` and other options leading `routes!`, which are also the
+    /// arguments of `#[controller]`.
+    pub(crate) fn parse_options(input: ParseStream) -> syn::Result<Self> {
         let (vis, name) = {
             input.parse::<kw::name>()?;
             input.parse::<Token![=>]>()?;
             let vis = input.parse::<Visibility>()?;
             let name = input.parse()?;
-            input.parse::<Token![,]>()?;
+            if !input.is_empty() {
+                input.parse::<Token![,]>()?;
+            }
             (vis, name)
         };
-        let state = if input.peek(kw::state) {
-            input.parse::<kw::state>()?;
-            input.parse::<Token![=>]>()?;
-            let state = Some(input.parse()?);
+        let mut state = None;
+        let mut rejection = None;
+        let mut method_not_allowed = None;
+        let mut not_acceptable = None;
+        let mut fallback: Option<Path> = None;
+        let mut fallback_service: Option<Expr> = None;
+        let mut layers: Vec<Layer> = Vec::new();
+        let mut trailing_slash = None;
+        loop {
+            if input.peek(kw::state) {
+                let keyword = input.parse::<kw::state>()?;
+                parse_option(input, keyword.span, "state", &mut state)?;
+            } else if input.peek(kw::rejection) {
+                let keyword = input.parse::<kw::rejection>()?;
+                parse_option(input, keyword.span, "rejection", &mut rejection)?;
+            } else if input.peek(kw::method_not_allowed) {
+                let keyword = input.parse::<kw::method_not_allowed>()?;
+                parse_option(
+                    input,
+                    keyword.span,
+                    "method_not_allowed",
+                    &mut method_not_allowed,
+                )?;
+            } else if input.peek(kw::not_acceptable) {
+                let keyword = input.parse::<kw::not_acceptable>()?;
+                parse_option(input, keyword.span, "not_acceptable", &mut not_acceptable)?;
+            } else if input.peek(kw::fallback_service) {
+                let keyword = input.parse::<kw::fallback_service>()?;
+                parse_option(
+                    input,
+                    keyword.span,
+                    "fallback_service",
+                    &mut fallback_service,
+                )?;
+            } else if input.peek(kw::fallback) {
+                let keyword = input.parse::<kw::fallback>()?;
+                parse_option(input, keyword.span, "fallback", &mut fallback)?;
+            } else if input.peek(kw::trailing_slash) {
+                let keyword = input.parse::<kw::trailing_slash>()?;
+                parse_option(input, keyword.span, "trailing_slash", &mut trailing_slash)?;
+            } else if input.peek(kw::layer) || input.peek(kw::route_layer) {
+                layers.push(input.parse()?);
+                if !input.is_empty() {
+                    input.parse::<Token![,]>()?;
+                }
+            } else {
+                break;
+            }
+        }
+        if let (Some(_), Some(fallback_service)) = (&fallback, &fallback_service) {
+            return Err(syn::Error::new(
+                fallback_service.span(),
+                "fallback and fallback_service can't be used together",
+            ));
+        }
+        Ok(Routes {
+            name,
+            vis,
+            state,
+            rejection,
+            method_not_allowed,
+            not_acceptable,
+            fallback,
+            fallback_service,
+            layers,
+            trailing_slash,
+            entries: Vec::new(),
+        })
+    }
+
+    pub(crate) fn set_routes(&mut self, routes: Vec<Route>) {
+        self.entries = routes
+            .into_iter()
+            .map(|route| Entry {
+                attrs: Vec::new(),
+                kind: EntryKind::Route(Box::new(route)),
+            })
+            .collect();
+    }
+}
+
+/// Rejects paths served both inside and outside host blocks, which the host blocks would
+/// answer for every host.
+fn check_host_paths(entries: &[Entry]) -> syn::Result<()> {
+    let mut host_paths = Vec::new();
+    let mut paths = Vec::new();
+    for entry in entries {
+        match &entry.kind {
+            EntryKind::Host(host) => entry_paths(&host.entries, &mut host_paths),
+            _ => entry_paths(std::slice::from_ref(entry), &mut paths),
+        }
+    }
+    for path in paths {
+        if host_paths.iter().any(|other| other.value() == path.value()) {
+            return Err(syn::Error::new_spanned(
+                path,
+                format!(
+                    "`{}` can't be served both inside and outside host blocks",
+                    path.value()
+                ),
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Collects the paths of the routes and entries without a handler among `entries`, with their
+/// aliases and the ones of their blocks.
+fn entry_paths<'a>(entries: &'a [Entry], paths: &mut Vec<&'a LitStr>) {
+    for entry in entries {
+        match &entry.kind {
+            EntryKind::Route(route) => {
+                paths.extend(&route.path);
+                paths.extend(route.aliases.iter().map(|alias| &alias.path));
+            }
+            EntryKind::Group(Group { entries, .. })
+            | EntryKind::Host(HostBlock { entries, .. }) => entry_paths(entries, paths),
+            EntryKind::Response { path, .. } => paths.push(path),
+            EntryKind::Merge(_) | EntryKind::Nest { .. } => {}
+        }
+    }
+}
+
+fn parse_entries(input: ParseStream, scope: Scope) -> syn::Result<Vec<Entry>> {
+    let mut entries = Vec::new();
+    let mut count = 0;
+    while !input.is_empty() {
+        if count > 0 {
             input.parse::<Token![,]>()?;
-            state
+            if input.is_empty() {
+                break;
+            }
+        }
+        let attrs = parse_attrs(input)?;
+        let kind = parse_entry(input, scope)?;
+        if let Some(attr) = deprecated(&attrs) {
+            if !matches!(&kind, EntryKind::Route(route) if route.definition.is_some()) {
+                return Err(syn::Error::new_spanned(
+                    attr,
+                    "`#[deprecated]` can only be put on routes with an inline path struct",
+                ));
+            }
+        }
+        entries.push(Entry { attrs, kind });
+        count += 1;
+    }
+    Ok(entries)
+}
+
+fn parse_entry(input: ParseStream, scope: Scope) -> syn::Result<EntryKind> {
+    let prefix = scope.prefix;
+    if input.peek(kw::merge) {
+        let keyword = input.parse::<kw::merge>()?;
+        if scope.host.is_some() {
+            return Err(syn::Error::new(
+                keyword.span,
+                "routes can't be merged inside a host block",
+            ));
+        }
+        if scope.prefix.is_some() {
+            return Err(syn::Error::new(
+                keyword.span,
+                "routes can't be merged inside a group, nest them at its prefix instead",
+            ));
+        }
+        input.parse::<Token![=>]>()?;
+        return Ok(EntryKind::Merge(input.parse()?));
+    }
+    if input.peek(kw::host) && input.peek2(LitStr) {
+        let keyword = input.parse::<kw::host>()?;
+        if prefix.is_some() || scope.host.is_some() {
+            return Err(syn::Error::new(
+                keyword.span,
+                "host blocks can only be used at the top of `routes!`",
+            ));
+        }
+        return Ok(EntryKind::Host(HostBlock::parse(input)?));
+    }
+    if !input.peek(LitStr) {
+        let route = Route::parse(input, None, None)?;
+        if prefix.is_some() || scope.host.is_some() {
+            return Err(syn::Error::new(
+                route.path_struct.span(),
+                "routes without a path literal can't be used inside a group or a host block, \
+                 their path is fixed by `#[typed_path]`",
+            ));
+        }
+        return Ok(EntryKind::Route(Box::new(route)));
+    }
+    let path: LitStr = input.parse()?;
+    check_path(&path)?;
+    let path = match prefix {
+        Some(prefix) => join_path(prefix, &path),
+        None => path,
+    };
+    let mut aliases = Vec::new();
+    while input.peek(Token![|]) {
+        input.parse::<Token![|]>()?;
+        let redirect = input.parse::<Option<kw::redirect>>()?.is_some();
+        let path: LitStr = input.parse()?;
+        check_path(&path)?;
+        let path = match prefix {
+            Some(prefix) => join_path(prefix, &path),
+            None => path,
+        };
+        aliases.push(Alias { path, redirect });
+    }
+    input.parse::<Token![=>]>()?;
+    let response = (input.peek(kw::redirect) && !input.peek2(syn::token::Brace))
+        || (input.peek(kw::text) && input.peek2(LitStr))
+        || (input.peek(kw::status) && input.peek2(LitInt));
+    if let Some(alias) = aliases.first() {
+        if response
+            || input.peek(kw::nest)
+            || (input.peek(kw::group) && input.peek2(syn::token::Brace))
+        {
+            return Err(syn::Error::new_spanned(
+                &alias.path,
+                "aliases are only supported on routes",
+            ));
+        }
+    }
+    if input.peek(kw::nest) {
+        let keyword = input.parse::<kw::nest>()?;
+        if scope.host.is_some() {
+            return Err(syn::Error::new(
+                keyword.span,
+                "routes can't be nested inside a host block",
+            ));
+        }
+        if scope.has_requirements {
+            return Err(syn::Error::new(
+                keyword.span,
+                "routes can't be nested inside a block with a required header or a guard",
+            ));
+        }
+        if path.value() == "/" {
+            return Err(syn::Error::new_spanned(
+                path,
+                "routes can't be nested at the root, use `merge => ...` instead",
+            ));
+        }
+        check_prefix(&path, "nest")?;
+        if path.value().contains('{') {
+            return Err(syn::Error::new_spanned(
+                path,
+                "nest prefixes can't contain typed captures",
+            ));
+        }
+        // The nested table isn't checked against the prefix, so its constraints would be ignored.
+        if path.value().contains('<') {
+            return Err(syn::Error::new_spanned(
+                path,
+                "nest prefixes can't contain constrained captures",
+            ));
+        }
+        Ok(EntryKind::Nest {
+            prefix: path,
+            routes: input.parse()?,
+        })
+    } else if input.peek(kw::group) && input.peek2(syn::token::Brace) {
+        Ok(EntryKind::Group(Group::parse(input, path, scope)?))
+    } else if response {
+        let (path, segments) = parse_path(&path)?;
+        let typed = |segment: &Segment| match segment {
+            Segment::Capture(capture) => capture.ty.is_some() || capture.constraint.is_some(),
+            Segment::Static(_) => false,
+        };
+        if segments.iter().any(typed) {
+            return Err(syn::Error::new_spanned(
+                path,
+                "entries without a handler can't have typed or constrained captures",
+            ));
+        }
+        Ok(EntryKind::Response {
+            path,
+            response: input.parse()?,
+        })
+    } else {
+        let mut route = Route::parse(input, Some(path), scope.host)?;
+        route.set_aliases(aliases)?;
+        Ok(EntryKind::Route(Box::new(route)))
+    }
+}
+
+/// Parses the outer attributes of an entry or a method handler, which can only be `#[cfg]`,
+/// `#[doc]` and `#[deprecated]`.
+fn parse_attrs(input: ParseStream) -> syn::Result<Vec<Attribute>> {
+    let attrs = input.call(Attribute::parse_outer)?;
+    for attr in &attrs {
+        if !["cfg", "doc", "deprecated"]
+            .iter()
+            .any(|name| attr.path().is_ident(name))
+        {
+            return Err(syn::Error::new_spanned(
+                attr,
+                "expected `#[cfg]`, `#[doc]` or `#[deprecated]`",
+            ));
+        }
+    }
+    Ok(attrs)
+}
+
+/// The `#[deprecated]` attribute among `attrs`, which is put on inline path structs.
+fn deprecated(attrs: &[Attribute]) -> Option<&Attribute> {
+    attrs.iter().find(|attr| attr.path().is_ident("deprecated"))
+}
+
+/// The `#[cfg]` attributes among `attrs`.
+pub(crate) fn cfgs(attrs: &[Attribute]) -> impl Iterator<Item = &Attribute> + Clone {
+    attrs.iter().filter(|attr| attr.path().is_ident("cfg"))
+}
+
+/// A `bool` expression that is `true` when every `#[cfg]` among `attrs` is enabled.
+fn cfg_enabled(attrs: &[Attribute]) -> proc_macro2::TokenStream {
+    let predicates = cfgs(attrs).map(|attr| match &attr.meta {
+        syn::Meta::List(list) => list.tokens.clone(),
+        meta => meta.to_token_stream(),
+    });
+    quote! { ::std::cfg!(all(#(#predicates),*)) }
+}
+
+/// Puts `statement` behind `cfgs`, in a block since attributes on assignments are unstable.
+fn cfg_statement<'a>(
+    cfgs: impl IntoIterator<Item = &'a Attribute>,
+    statement: proc_macro2::TokenStream,
+) -> proc_macro2::TokenStream {
+    let mut cfgs = cfgs.into_iter().peekable();
+    if cfgs.peek().is_none() {
+        return statement;
+    }
+    quote! {
+        #(#cfgs)*
+        {
+            #statement
+        }
+    }
+}
+
+/// The braced block of a group or a host block.
+struct Block {
+    state: Option<Path>,
+    layers: Vec<Layer>,
+    requirements: Vec<Requirement>,
+    entries: Vec<Entry>,
+}
+
+/// Parses the braced block of a group or a host block: its `state`, layers and requirements,
+/// then its entries.
+fn parse_block(input: ParseStream, span: Span, scope: Scope) -> syn::Result<Block> {
+    let content;
+    let _ = braced!(content in input);
+    let mut state = None;
+    let mut layers = Vec::new();
+    let mut requirements = Vec::new();
+    loop {
+        if content.peek(kw::state) {
+            let keyword = content.parse::<kw::state>()?;
+            parse_option(&content, keyword.span, "state", &mut state)?;
+        } else if content.peek(kw::layer) || content.peek(kw::route_layer) {
+            layers.push(content.parse()?);
+            if !content.is_empty() {
+                content.parse::<Token![,]>()?;
+            }
+        } else if Requirement::peek(&content) {
+            push_requirement(&mut requirements, content.parse()?)?;
+            if !content.is_empty() {
+                content.parse::<Token![,]>()?;
+            }
         } else {
-            None
+            break;
+        }
+    }
+    let scope = Scope {
+        has_requirements: scope.has_requirements || !requirements.is_empty(),
+        ..scope
+    };
+    let entries = parse_entries(&content, scope)?;
+    if entries.is_empty() {
+        return Err(syn::Error::new(span, "expected at least one route"));
+    }
+    Ok(Block {
+        state,
+        layers,
+        requirements,
+        entries,
+    })
+}
+
+impl Group {
+    fn parse(input: ParseStream, prefix: LitStr, scope: Scope) -> syn::Result<Self> {
+        input.parse::<kw::group>()?;
+        check_prefix(&prefix, "group")?;
+        let hosted = scope.host.is_some();
+        let scope = Scope {
+            prefix: Some(&prefix),
+            ..scope
         };
-        let rejection = if input.peek(kw::rejection) {
+        let Block {
+            state,
+            layers,
+            requirements,
+            entries,
+        } = parse_block(input, prefix.span(), scope)?;
+        Ok(Group {
+            prefix,
+            hosted,
+            state,
+            layers,
+            requirements,
+            entries,
+        })
+    }
+}
+
+impl HostBlock {
+    /// Parses a host block after its `host` keyword.
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let host = Host::parse(input.parse()?)?;
+        input.parse::<Token![=>]>()?;
+        let scope = Scope {
+            prefix: None,
+            host: Some(&host),
+            has_requirements: false,
+        };
+        let Block {
+            state,
+            layers,
+            requirements,
+            entries,
+        } = parse_block(input, host.pattern.span(), scope)?;
+        Ok(HostBlock {
+            host,
+            state,
+            layers,
+            requirements,
+            entries,
+        })
+    }
+}
+
+impl Route {
+    /// Starts a route without handlers, for `#[controller]` methods.
+    pub(crate) fn new(path: Option<LitStr>, path_struct: Path) -> syn::Result<Self> {
+        let (path, segments) = match path {
+            Some(path) => {
+                let (path, segments) = parse_path(&path)?;
+                (Some(path), segments)
+            }
+            None => (None, Vec::new()),
+        };
+        Ok(Route {
+            path,
+            segments,
+            path_struct,
+            generics: Generics::default(),
+            tuple: false,
+            definition: None,
+            aliases: Vec::new(),
+            host: None,
+            rejection: None,
+            endpoint: Endpoint::default(),
+        })
+    }
+
+    pub(crate) fn path(&self) -> Option<&LitStr> {
+        self.path.as_ref()
+    }
+
+    pub(crate) fn path_struct(&self) -> &Path {
+        &self.path_struct
+    }
+
+    /// Adds `handler` for `method`, one of the standard methods or `any`, under the `#[cfg]`
+    /// attributes among `attrs`.
+    pub(crate) fn add_handler(
+        &mut self,
+        method: Ident,
+        handler: Path,
+        attrs: &[Attribute],
+    ) -> syn::Result<()> {
+        let attrs = cfgs(attrs).cloned().collect::<Vec<_>>();
+        if method == "any" {
+            if let Some(attr) = attrs.first() {
+                return Err(syn::Error::new_spanned(
+                    attr,
+                    "`#[cfg]` is not supported on any handlers",
+                ));
+            }
+            if self.endpoint.any_handler.is_some() {
+                return Err(syn::Error::new(method.span(), "duplicate any handler"));
+            }
+            self.endpoint.any_handler = Some(handler);
+            return Ok(());
+        }
+        push_handler(
+            &mut self.endpoint.handlers,
+            MethodHandler {
+                attrs,
+                methods: vec![Method::Standard(method)],
+                content_type: None,
+                accept: None,
+                guard: None,
+                handler,
+            },
+        )
+    }
+
+    fn parse(input: ParseStream, path: Option<LitStr>, host: Option<&Host>) -> syn::Result<Self> {
+        let (path, segments) = match path {
+            Some(path) => {
+                let (path, segments) = parse_path(&path)?;
+                (Some(path), segments)
+            }
+            None => (None, Vec::new()),
+        };
+        let rejection: Option<Path> = if path.is_some() && input.peek(kw::rejection) {
             input.parse::<kw::rejection>()?;
-            input.parse::<Token![=>]>()?;
             let rejection = Some(input.parse()?);
-            input.parse::<Token![,]>()?;
+            input.parse::<Token![=>]>()?;
             rejection
         } else {
-            None
+            None
+        };
+        // Host captures come first, as fields of the path struct.
+        let captures = host
+            .iter()
+            .flat_map(|host| &host.segments)
+            .chain(&segments)
+            .cloned()
+            .collect::<Vec<_>>();
+        // Derived path structs implement `TypedPath` themselves, so their arguments are types.
+        let (path_struct, generics, tuple, definition) = if path.is_some() {
+            let vis: Visibility = input.parse()?;
+            let struct_token: Option<Token![struct]> = input.parse()?;
+            let path_struct = Path::parse_mod_style(input)?;
+            let mut generics: Generics = input.parse()?;
+            let mut fields = None;
+            let tuple = input.peek(syn::token::Paren);
+            if tuple {
+                let content;
+                let paren_token = parenthesized!(content in input);
+                if content.peek(Token![..]) {
+                    content.parse::<Token![..]>()?;
+                } else {
+                    fields = Some(Fields::Unnamed(FieldsUnnamed {
+                        paren_token,
+                        unnamed: content.parse_terminated(Field::parse_unnamed, Token![,])?,
+                    }));
+                }
+            }
+            generics.where_clause = input.parse()?;
+            if let Some(param) = generics.const_params().next() {
+                return Err(syn::Error::new_spanned(
+                    param,
+                    "const generic path structs are not supported",
+                ));
+            }
+            // Named fields are followed by the handlers, in a second pair of braces.
+            let fork = input.fork();
+            let named = fork
+                .step(|cursor| match cursor.group(Delimiter::Brace) {
+                    Some((_, _, rest)) => Ok(((), rest)),
+                    None => Err(cursor.error("expected braces")),
+                })
+                .is_ok()
+                && fork.peek(syn::token::Brace);
+            if named {
+                fields = Some(Fields::Named(input.parse()?));
+            }
+            if let Some(struct_token) = struct_token {
+                if fields.is_some() || tuple {
+                    return Err(syn::Error::new_spanned(
+                        struct_token,
+                        "`struct` path structs take their fields from the typed captures",
+                    ));
+                }
+                fields = Some(Fields::Named(fields_from_captures(
+                    &captures,
+                    &vis,
+                    struct_token.span,
+                )?));
+            }
+            let definition = match fields {
+                Some(fields) => {
+                    let Some(ident) = path_struct.get_ident() else {
+                        return Err(syn::Error::new_spanned(
+                            path_struct,
+                            "inline path structs must be named by an identifier",
+                        ));
+                    };
+                    Some(ItemStruct {
+                        attrs: Vec::new(),
+                        vis,
+                        struct_token: Default::default(),
+                        ident: ident.clone(),
+                        generics: generics.clone(),
+                        fields,
+                        semi_token: tuple.then(Default::default),
+                    })
+                }
+                None => {
+                    if !matches!(vis, Visibility::Inherited) {
+                        return Err(syn::Error::new_spanned(
+                            vis,
+                            "visibility can only be set on inline path structs",
+                        ));
+                    }
+                    None
+                }
+            };
+            (path_struct, generics, tuple, definition)
+        } else {
+            (input.parse()?, Generics::default(), false, None)
+        };
+        let content;
+        let _ = braced!(content in input);
+        let endpoint: Endpoint = content.parse()?;
+        if endpoint.handlers.is_empty() && endpoint.any_handler.is_none() {
+            return Err(syn::Error::new(
+                path_struct.span(),
+                "expected at least one handler",
+            ));
+        }
+        if let Some(definition) = &definition {
+            check_fields(definition, &captures)?;
+        }
+        Ok(Route {
+            endpoint,
+            path,
+            segments,
+            path_struct,
+            generics,
+            tuple,
+            definition,
+            aliases: Vec::new(),
+            host: host.cloned(),
+            rejection,
+        })
+    }
+
+    /// Adds the `aliases` of the route, which must have the same captures as its path.
+    fn set_aliases(&mut self, aliases: Vec<Alias>) -> syn::Result<()> {
+        let capture_names = |segments: &[Segment]| {
+            let mut names = segments
+                .iter()
+                .filter_map(|segment| match segment {
+                    Segment::Capture(capture) => Some(capture.name.clone()),
+                    Segment::Static(_) => None,
+                })
+                .collect::<Vec<_>>();
+            names.sort();
+            names
+        };
+        let captures = capture_names(&self.segments);
+        self.aliases = aliases
+            .into_iter()
+            .map(|Alias { path, redirect }| {
+                let (path, segments) = parse_path(&path)?;
+                if capture_names(&segments) != captures {
+                    return Err(syn::Error::new_spanned(
+                        path,
+                        "aliases must have the same captures as the path of their route",
+                    ));
+                }
+                Ok(Alias { path, redirect })
+            })
+            .collect::<syn::Result<_>>()?;
+        Ok(())
+    }
+}
+
+/// The fields of a `struct` path struct, one for each typed capture of its path.
+fn fields_from_captures(
+    segments: &[Segment],
+    vis: &Visibility,
+    span: Span,
+) -> syn::Result<FieldsNamed> {
+    let fields = segments
+        .iter()
+        .filter_map(|segment| match segment {
+            Segment::Capture(capture) => Some((&capture.name, capture.span, &capture.ty)),
+            Segment::Static(_) => None,
+        })
+        .map(|(capture, span, ty)| {
+            let ident = format_ident!("{}", capture, span = span);
+            let ty = ty.as_ref().ok_or_else(|| {
+                syn::Error::new(
+                    span,
+                    format!("expected a type for `{capture}`, as in `{{{capture}: Type}}`"),
+                )
+            })?;
+            Ok(quote! { #vis #ident: #ty })
+        })
+        .collect::<syn::Result<Vec<_>>>()?;
+    Ok(parse_quote_spanned! { span => { #(#fields),* } })
+}
+
+/// Checks that the fields of an inline path struct match the captures of its path. Named fields
+/// with attributes, like `#[serde(skip)]`, don't need a capture.
+fn check_fields(definition: &ItemStruct, segments: &[Segment]) -> syn::Result<()> {
+    let captures = segments
+        .iter()
+        .filter_map(|segment| match segment {
+            Segment::Capture(capture) => Some((&capture.name, capture.span)),
+            Segment::Static(_) => None,
+        })
+        .collect::<Vec<_>>();
+    match &definition.fields {
+        Fields::Named(fields) => {
+            for (capture, span) in &captures {
+                if !fields
+                    .named
+                    .iter()
+                    .any(|field| field.ident.as_ref().is_some_and(|ident| ident == capture))
+                {
+                    return Err(syn::Error::new(
+                        *span,
+                        format!("`{}` has no field `{capture}`", definition.ident),
+                    ));
+                }
+            }
+            for field in &fields.named {
+                let ident = field.ident.as_ref().expect("named field");
+                if field.attrs.is_empty() && !captures.iter().any(|(capture, _)| ident == *capture)
+                {
+                    return Err(syn::Error::new_spanned(
+                        ident,
+                        format!("no capture `:{ident}` in the path"),
+                    ));
+                }
+            }
+        }
+        Fields::Unnamed(fields) if fields.unnamed.len() != captures.len() => {
+            return Err(syn::Error::new_spanned(
+                fields,
+                format!("expected {} fields, one for each capture", captures.len()),
+            ));
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+/// Parses the `=> value,` following the keyword of a top-level or group option.
+fn parse_option<T: Parse>(
+    input: ParseStream,
+    keyword: Span,
+    name: &str,
+    option: &mut Option<T>,
+) -> syn::Result<()> {
+    input.parse::<Token![=>]>()?;
+    if option.is_some() {
+        return Err(syn::Error::new(keyword, format!("duplicate {name} option")));
+    }
+    *option = Some(input.parse()?);
+    if !input.is_empty() {
+        input.parse::<Token![,]>()?;
+    }
+    Ok(())
+}
+
+fn check_path(path: &LitStr) -> syn::Result<()> {
+    let value = path.value();
+    if value.is_empty() {
+        return Err(syn::Error::new_spanned(
+            path,
+            "paths must start with a `/`. Use \"/\" for root routes",
+        ));
+    } else if !value.starts_with('/') {
+        return Err(syn::Error::new_spanned(path, "paths must start with a `/`"));
+    }
+    Ok(())
+}
+
+/// Checks the prefix of a `group` or `nest` entry, `kind` being used in the error messages.
+fn check_prefix(prefix: &LitStr, kind: &str) -> syn::Result<()> {
+    let value = prefix.value();
+    if value.ends_with('/') {
+        return Err(syn::Error::new_spanned(
+            prefix,
+            format!("{kind} prefixes must not end with a `/`"),
+        ));
+    }
+    if value.split('/').any(|segment| segment.starts_with('*')) {
+        return Err(syn::Error::new_spanned(
+            prefix,
+            format!("{kind} prefixes can't contain wildcard captures"),
+        ));
+    }
+    Ok(())
+}
+
+/// Prepends a group prefix to `path`, keeping the span of `path`. `"/"` maps to the prefix itself.
+fn join_path(prefix: &LitStr, path: &LitStr) -> LitStr {
+    let path_value = path.value();
+    let joined = match path_value.as_str() {
+        "/" => prefix.value(),
+        _ => format!("{}{path_value}", prefix.value()),
+    };
+    LitStr::new(&joined, path.span())
+}
+
+/// Splits `path` into its segments, and returns it with its captures written the way axum
+/// expects them: typed captures, `{id: u64}` or `{*rest: String}`, become `:id` or `*rest`, and
+/// constraints, `:id<\d+>` or `{page<1..=1000>: u32}`, are removed.
+pub(crate) fn parse_path(path: &LitStr) -> syn::Result<(LitStr, Vec<Segment>)> {
+    check_path(path)?;
+
+    let mut axum_segments = Vec::new();
+    let segments = path
+        .value()
+        .split('/')
+        .map(|segment| {
+            let (capture, braced) = match segment
+                .strip_prefix('{')
+                .and_then(|segment| segment.strip_suffix('}'))
+            {
+                Some(capture) => (capture.trim(), true),
+                None if segment.starts_with(':') || segment.starts_with('*') => (segment, false),
+                None => {
+                    axum_segments.push(segment.to_owned());
+                    return Ok(Segment::Static(segment.to_owned()));
+                }
+            };
+            let (prefix, capture) = match capture.strip_prefix('*') {
+                Some(capture) => ('*', capture),
+                None => (':', capture.strip_prefix(':').unwrap_or(capture)),
+            };
+            let end = capture
+                .find(|c: char| !c.is_alphanumeric() && c != '_')
+                .unwrap_or(capture.len());
+            let (name, rest) = capture.split_at(end);
+            if syn::parse_str::<Ident>(name).is_err() {
+                return Err(syn::Error::new_spanned(
+                    path,
+                    format!("invalid capture name `{name}`"),
+                ));
+            }
+            let (constraint, rest) = split_constraint(rest, path)?;
+            let ty = match rest.trim().strip_prefix(':') {
+                Some(ty) if braced => Some(parse_capture_type(ty, path)?),
+                None if rest.trim().is_empty() => None,
+                _ => {
+                    return Err(syn::Error::new_spanned(
+                        path,
+                        format!("unexpected `{rest}` after capture `{name}`"),
+                    ))
+                }
+            };
+            axum_segments.push(format!("{prefix}{name}"));
+            let range = constraint.as_deref().and_then(parse_range);
+            if let (Some(constraint), None) = (&constraint, &range) {
+                regex_syntax::Parser::new()
+                    .parse(constraint)
+                    .map_err(|error| {
+                        syn::Error::new_spanned(
+                            path,
+                            format!("invalid constraint `{constraint}`: {error}"),
+                        )
+                    })?;
+            }
+            Ok(Segment::Capture(Box::new(Capture {
+                name: name.to_owned(),
+                span: path.span(),
+                ty,
+                constraint,
+                range,
+            })))
+        })
+        .collect::<syn::Result<_>>()?;
+    Ok((LitStr::new(&axum_segments.join("/"), path.span()), segments))
+}
+
+/// Splits the `<...>` constraint off the start of `rest`, matching nested angle brackets.
+fn split_constraint<'a>(rest: &'a str, path: &LitStr) -> syn::Result<(Option<String>, &'a str)> {
+    if !rest.starts_with('<') {
+        return Ok((None, rest));
+    }
+    let mut depth = 0;
+    for (index, c) in rest.char_indices() {
+        match c {
+            '<' => depth += 1,
+            '>' => depth -= 1,
+            _ => {}
+        }
+        if depth == 0 {
+            return Ok((Some(rest[1..index].to_owned()), &rest[index + 1..]));
+        }
+    }
+    Err(syn::Error::new_spanned(path, "unclosed `<` in capture"))
+}
+
+/// Parses a constraint like `1..=1000`, with integer bounds, as a range.
+fn parse_range(constraint: &str) -> Option<ExprRange> {
+    let is_int = |expr: &Expr| match expr {
+        Expr::Lit(ExprLit {
+            lit: Lit::Int(_), ..
+        }) => true,
+        Expr::Unary(unary) => matches!(
+            (&unary.op, &*unary.expr),
+            (
+                syn::UnOp::Neg(_),
+                Expr::Lit(ExprLit {
+                    lit: Lit::Int(_),
+                    ..
+                })
+            )
+        ),
+        _ => false,
+    };
+    let range = syn::parse_str::<ExprRange>(constraint).ok()?;
+    let bounds = [&range.start, &range.end];
+    bounds
+        .iter()
+        .all(|bound| bound.as_deref().is_none_or(is_int))
+        .then_some(range)
+}
+
+/// Parses the type of a typed capture, spanned at the path literal for error messages.
+fn parse_capture_type(ty: &str, path: &LitStr) -> syn::Result<Type> {
+    let tokens: proc_macro2::TokenStream = ty.parse().map_err(|_| {
+        syn::Error::new_spanned(path, format!("invalid capture type `{}`", ty.trim()))
+    })?;
+    let tokens = respan(tokens, path.span());
+    syn::parse2(tokens)
+        .map_err(|_| syn::Error::new_spanned(path, format!("invalid capture type `{}`", ty.trim())))
+}
+
+fn respan(tokens: proc_macro2::TokenStream, span: Span) -> proc_macro2::TokenStream {
+    tokens
+        .into_iter()
+        .map(|mut token| {
+            if let proc_macro2::TokenTree::Group(group) = &token {
+                let mut respanned =
+                    proc_macro2::Group::new(group.delimiter(), respan(group.stream(), span));
+                respanned.set_span(span);
+                token = respanned.into();
+            }
+            token.set_span(span);
+            token
+        })
+        .collect()
+}
+
+#[derive(Debug, Clone)]
+pub(crate) enum Segment {
+    Capture(Box<Capture>),
+    Static(String),
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct Capture {
+    name: String,
+    span: Span,
+    /// The type of a typed capture, `{id: u64}`.
+    ty: Option<Type>,
+    /// The constraint between `<` and `>`, matched as a regex unless it is a `range`.
+    constraint: Option<String>,
+    range: Option<ExprRange>,
+}
+
+impl Routes {
+    fn state_ty(&self) -> proc_macro2::TokenStream {
+        self.state
+            .as_ref()
+            .map(ToTokens::to_token_stream)
+            .unwrap_or_else(|| quote! { () })
+    }
+
+    /// Emits the impls for every route in `entries` into `tokens` and returns the statements
+    /// registering them on the router `r`. `sub_state` is the state declared by the innermost
+    /// enclosing group, if any, `required` the requirements of the enclosing blocks, with the
+    /// state of each block, and `cfgs` the `#[cfg]` attributes of the enclosing groups.
+    fn entries_to_tokens(
+        &self,
+        entries: &[Entry],
+        sub_state: Option<&Path>,
+        required: &[(&Requirement, Option<&Path>)],
+        cfgs: &[&Attribute],
+        tokens: &mut proc_macro2::TokenStream,
+    ) -> Vec<proc_macro2::TokenStream> {
+        entries
+            .iter()
+            .map(|Entry { attrs, kind }| {
+                let own_cfgs = self::cfgs(attrs).collect::<Vec<_>>();
+                let cfgs = cfgs
+                    .iter()
+                    .copied()
+                    .chain(own_cfgs.iter().copied())
+                    .collect::<Vec<_>>();
+                let statement = match kind {
+                    EntryKind::Route(route) => {
+                        let statement =
+                            self.route_to_tokens(route, attrs, sub_state, required, &cfgs, tokens);
+                        // The route itself uses its deprecated path struct.
+                        if deprecated(attrs).is_some() {
+                            quote! {
+                                #[allow(deprecated)]
+                                {
+                                    #statement
+                                }
+                            }
+                        } else {
+                            statement
+                        }
+                    }
+                    EntryKind::Group(group) => {
+                        self.group_to_tokens(group, sub_state, required, &cfgs, tokens)
+                    }
+                    EntryKind::Host(host) => self.host_to_tokens(host, &cfgs, tokens),
+                    // Merged the same way as groups, to keep their layers off our fallback.
+                    EntryKind::Merge(routes) => {
+                        let state_ty = self.state_ty();
+                        quote_spanned! {
+                            routes.span() =>
+                            r = {
+                                let other: ::axum::Router<#state_ty> = <#routes>::routes();
+                                other
+                            }
+                            .merge(r);
+                        }
+                    }
+                    EntryKind::Nest { prefix, routes } => {
+                        let state_ty = self.state_ty();
+                        quote_spanned! {
+                            routes.span() =>
+                            r = r.nest(#prefix, {
+                                let other: ::axum::Router<#state_ty> = <#routes>::routes();
+                                other
+                            });
+                        }
+                    }
+                    EntryKind::Response { path, response } => route_statement(
+                        path.span(),
+                        self.trailing_slash,
+                        path,
+                        &apply_requirements(&self.state_ty(), required, response.to_token_stream()),
+                    ),
+                };
+                cfg_statement(own_cfgs, statement)
+            })
+            .collect()
+    }
+
+    fn group_to_tokens(
+        &self,
+        Group {
+            prefix,
+            hosted,
+            state,
+            layers,
+            requirements,
+            entries,
+        }: &Group,
+        sub_state: Option<&Path>,
+        required: &[(&Requirement, Option<&Path>)],
+        cfgs: &[&Attribute],
+        tokens: &mut proc_macro2::TokenStream,
+    ) -> proc_macro2::TokenStream {
+        let state_ty = self.state_ty();
+        let sub_state = state.as_ref().or(sub_state);
+        let required = required
+            .iter()
+            .copied()
+            .chain(
+                requirements
+                    .iter()
+                    .map(|requirement| (requirement, sub_state)),
+            )
+            .collect::<Vec<_>>();
+        let routes_fn = self.entries_to_tokens(entries, sub_state, &required, cfgs, tokens);
+        let layers = layers.iter().map(router_layer);
+        let router = if *hosted {
+            quote! { ::better_routes::__private::HostRoutes::<#state_ty> }
+        } else {
+            quote! { ::axum::Router::<#state_ty> }
         };
-        let mut routes = Vec::new();
-        let mut count = 0;
-        while !input.is_empty() {
-            if count > 0 {
-                input.parse::<Token![,]>()?;
-                if input.is_empty() {
-                    break;
-                }
+        // When neither router has a custom fallback, `merge` keeps the one of its argument,
+        // so the group is merged into the outer router to keep group layers off its fallback.
+        quote_spanned! {
+            prefix.span() =>
+            r = {
+                let mut r = #router::new();
+                #(#routes_fn)*
+                #(#layers)*
+                r
             }
-            let path: LitStr = input.parse()?;
-            input.parse::<Token![=>]>()?;
-            let rejection: Option<Path> = if input.peek(kw::rejection) {
-                input.parse::<kw::rejection>()?;
-                let rejection = Some(input.parse()?);
-                input.parse::<Token![=>]>()?;
+            .merge(r);
+        }
+    }
+
+    /// The statement adding the routes of a host block to `hosts`, which serves them once every
+    /// block is known.
+    fn host_to_tokens(
+        &self,
+        HostBlock {
+            host,
+            state,
+            layers,
+            requirements,
+            entries,
+        }: &HostBlock,
+        cfgs: &[&Attribute],
+        tokens: &mut proc_macro2::TokenStream,
+    ) -> proc_macro2::TokenStream {
+        let state_ty = self.state_ty();
+        let required = requirements
+            .iter()
+            .map(|requirement| (requirement, state.as_ref()))
+            .collect::<Vec<_>>();
+        let routes_fn = self.entries_to_tokens(entries, state.as_ref(), &required, cfgs, tokens);
+        let layers = layers.iter().map(router_layer);
+        let pattern = &host.pattern;
+        quote_spanned! {
+            pattern.span() =>
+            hosts.host(#pattern, {
+                let mut r = ::better_routes::__private::HostRoutes::<#state_ty>::new();
+                #(#routes_fn)*
+                #(#layers)*
+                r
+            });
+        }
+    }
+
+    fn route_to_tokens(
+        &self,
+        Route {
+            path,
+            segments,
+            path_struct,
+            generics,
+            tuple,
+            definition,
+            aliases,
+            host,
+            rejection,
+            endpoint,
+        }: &Route,
+        attrs: &[Attribute],
+        sub_state: Option<&Path>,
+        required: &[(&Requirement, Option<&Path>)],
+        cfgs: &[&Attribute],
+        tokens: &mut proc_macro2::TokenStream,
+    ) -> proc_macro2::TokenStream {
+        if let Some(path) = path {
+            let rejection = if rejection.is_some() {
                 rejection
             } else {
-                None
+                &self.rejection
             };
-            let path_struct: Path = input.parse()?;
-            let mut get_handler = None;
-            let mut post_handler = None;
-            let mut put_handler = None;
-            let mut patch_handler = None;
-            let mut delete_handler = None;
-            let mut count2 = 0;
-            let content;
-            let _ = braced!(content in input);
-            if content.is_empty() {
-                return Err(syn::Error::new(
-                    path_struct.span(),
-                    "expected at least one handler",
-                ));
-            }
-            while !content.is_empty() {
-                if count2 > 0 {
-                    content.parse::<Token![,]>()?;
-                    if content.is_empty() {
-                        break;
-                    }
-                }
-                if content.peek(kw::get) {
-                    content.parse::<kw::get>()?;
-                    content.parse::<Token![=>]>()?;
-                    if get_handler.is_some() {
-                        return Err(syn::Error::new(content.span(), "duplicate get handler"));
-                    }
-                    get_handler = Some(content.parse()?);
-                }
-                if content.peek(kw::post) {
-                    content.parse::<kw::post>()?;
-                    content.parse::<Token![=>]>()?;
-                    if post_handler.is_some() {
-                        return Err(syn::Error::new(content.span(), "duplicate post handler"));
-                    }
-                    post_handler = Some(content.parse()?);
-                }
-                if content.peek(kw::put) {
-                    content.parse::<kw::put>()?;
-                    content.parse::<Token![=>]>()?;
-                    if put_handler.is_some() {
-                        return Err(syn::Error::new(content.span(), "duplicate put handler"));
-                    }
-                    put_handler = Some(content.parse()?);
-                }
-                if content.peek(kw::patch) {
-                    content.parse::<kw::patch>()?;
-                    content.parse::<Token![=>]>()?;
-                    if patch_handler.is_some() {
-                        return Err(syn::Error::new(content.span(), "duplicate patch handler"));
-                    }
-                    patch_handler = Some(content.parse()?);
-                }
-                if content.peek(kw::delete) {
-                    content.parse::<kw::delete>()?;
-                    content.parse::<Token![=>]>()?;
-                    if delete_handler.is_some() {
-                        return Err(syn::Error::new(content.span(), "duplicate delete handler"));
-                    }
-                    delete_handler = Some(content.parse()?);
-                }
-
-                count2 += 1;
+            let deprecated = deprecated(attrs);
+            let docs = attrs.iter().filter(|attr| attr.path().is_ident("doc"));
+            let attrs = cfgs.iter().copied().chain(docs).collect::<Vec<_>>();
+            if let Some(definition) = definition {
+                let mut definition = definition.clone();
+                definition.attrs = attrs.iter().copied().chain(deprecated).cloned().collect();
+                definition.attrs.push(parse_quote! {
+                    #[derive(
+                        ::serde::Deserialize,
+                        ::std::fmt::Debug,
+                        ::std::clone::Clone,
+                        ::std::cmp::PartialEq,
+                        ::std::hash::Hash,
+                    )]
+                });
+                definition.to_tokens(tokens);
             }
-            let segments = parse_path(&path)?;
-            routes.push(Route {
-                get_handler,
-                post_handler,
-                put_handler,
-                patch_handler,
-                delete_handler,
+            tokens.extend(path_impls(
                 path,
                 segments,
+                host.as_ref(),
                 path_struct,
+                generics,
+                *tuple,
+                // The other fields of inline path structs are checked when parsing them.
+                !generics.params.is_empty() || definition.is_some(),
                 rejection,
-            });
-            count += 1;
+                &attrs,
+            ));
+        }
+
+        // The arguments of generic path structs are inferred from the handlers.
+        let params = generics.params.iter().map(|param| match param {
+            GenericParam::Lifetime(_) => quote! { '_ },
+            _ => quote! { _ },
+        });
+        let path_struct_ty = if generics.params.is_empty() {
+            path_struct.to_token_stream()
+        } else {
+            quote! { #path_struct<#(#params),*> }
+        };
+        let sub_state = endpoint.state.as_ref().or(sub_state);
+        let method_router = method_router(
+            &self.state_ty(),
+            sub_state,
+            &path_struct_ty,
+            endpoint,
+            &self.method_not_allowed,
+            &self.not_acceptable,
+        );
+        let required = required
+            .iter()
+            .copied()
+            .chain(
+                endpoint
+                    .requirements
+                    .iter()
+                    .map(|requirement| (requirement, sub_state)),
+            )
+            .collect::<Vec<_>>();
+        let method_router = apply_requirements(&self.state_ty(), &required, method_router);
+        let canonical = path;
+        let path = match path {
+            Some(path) if !generics.params.is_empty() => path.to_token_stream(),
+            _ => quote! { <#path_struct as ::axum_extra::routing::TypedPath>::PATH },
+        };
+        let trailing_slash = endpoint.trailing_slash.or(self.trailing_slash);
+        let route = |path: &dyn ToTokens, method_router: &proc_macro2::TokenStream| {
+            route_statement(path_struct.span(), trailing_slash, path, method_router)
+        };
+        if aliases.is_empty() {
+            return route(&path, &method_router);
+        }
+        let clone = quote! { ::std::clone::Clone::clone(&method_router) };
+        let aliases = aliases.iter().map(|Alias { path, redirect }| {
+            if *redirect {
+                quote_spanned! {
+                    path.span() =>
+                    r = r.route(#path, ::better_routes::__private::redirect_alias(#canonical));
+                }
+            } else {
+                route(path, &clone)
+            }
+        });
+        let route = route(&path, &clone);
+        quote! {
+            {
+                let method_router = #method_router;
+                #route
+                #(#aliases)*
+            }
         }
-        Ok(Routes {
-            name,
-            vis,
-            state,
-            rejection,
-            routes,
-        })
     }
 }
 
-fn parse_path(path: &LitStr) -> syn::Result<Vec<Segment>> {
-    let value = path.value();
-    if value.is_empty() {
-        return Err(syn::Error::new_spanned(
-            path,
-            "paths must start with a `/`. Use \"/\" for root routes",
-        ));
-    } else if !path.value().starts_with('/') {
-        return Err(syn::Error::new_spanned(path, "paths must start with a `/`"));
+/// Whether a route, block or method handler among `entries` has a guard.
+fn has_guards(entries: &[Entry]) -> bool {
+    let guard = |requirements: &[Requirement]| {
+        requirements
+            .iter()
+            .any(|requirement| matches!(requirement, Requirement::Guard(_)))
+    };
+    entries.iter().any(|entry| match &entry.kind {
+        EntryKind::Route(route) => {
+            guard(&route.endpoint.requirements)
+                || route
+                    .endpoint
+                    .handlers
+                    .iter()
+                    .any(|handler| handler.guard.is_some())
+        }
+        EntryKind::Group(group) => guard(&group.requirements) || has_guards(&group.entries),
+        EntryKind::Host(host) => guard(&host.requirements) || has_guards(&host.entries),
+        EntryKind::Merge(_) | EntryKind::Nest { .. } | EntryKind::Response { .. } => false,
+    })
+}
+
+/// Whether `entries` merge or nest another `routes!`.
+fn has_nested_tables(entries: &[Entry]) -> bool {
+    entries.iter().any(|entry| match &entry.kind {
+        EntryKind::Merge(_) | EntryKind::Nest { .. } => true,
+        EntryKind::Group(group) => has_nested_tables(&group.entries),
+        EntryKind::Host(host) => has_nested_tables(&host.entries),
+        EntryKind::Route(_) | EntryKind::Response { .. } => false,
+    })
+}
+
+/// Groups the `handlers` with a media type by the one `media` gives them, in the order the
+/// media types first appear.
+fn by_media<'a>(
+    handlers: &[&'a MethodHandler],
+    media: impl Fn(&'a MethodHandler) -> Option<&'a MediaType>,
+) -> Vec<(&'a str, Vec<&'a MethodHandler>)> {
+    let mut by_media: Vec<(&str, Vec<&MethodHandler>)> = Vec::new();
+    for handler in handlers {
+        let Some(media) = media(handler).map(|media| media.value.as_str()) else {
+            continue;
+        };
+        match by_media.iter_mut().find(|(other, _)| *other == media) {
+            Some((_, handlers)) => handlers.push(handler),
+            None => by_media.push((media, vec![handler])),
+        }
     }
+    by_media
+}
 
-    path.value()
-        .split('/')
-        .map(|segment| {
-            if let Some(capture) = segment
-                .strip_prefix(':')
-                .or_else(|| segment.strip_prefix('*'))
+/// Groups `handlers` by method, in the order the methods first appear.
+fn by_method<'a>(
+    handlers: impl Iterator<Item = &'a MethodHandler>,
+) -> Vec<(&'a Method, Vec<&'a MethodHandler>)> {
+    let mut by_method: Vec<(&Method, Vec<&MethodHandler>)> = Vec::new();
+    for handler in handlers {
+        for method in &handler.methods {
+            match by_method
+                .iter_mut()
+                .find(|(other, _)| other.name() == method.name())
             {
-                Ok(Segment::Capture(capture.to_owned(), path.span()))
-            } else {
-                Ok(Segment::Static(segment.to_owned()))
+                Some((_, handlers)) => handlers.push(handler),
+                None => by_method.push((method, vec![handler])),
+            }
+        }
+    }
+    by_method
+}
+
+/// Puts `statement` behind a check that one of `handlers` is enabled by its `#[cfg]`s, unless
+/// one of them has none.
+fn if_any_enabled(
+    handlers: &[&MethodHandler],
+    statement: proc_macro2::TokenStream,
+) -> proc_macro2::TokenStream {
+    if handlers
+        .iter()
+        .any(|handler| cfgs(&handler.attrs).next().is_none())
+    {
+        return statement;
+    }
+    let enabled = handlers.iter().map(|handler| cfg_enabled(&handler.attrs));
+    quote! {
+        if #(#enabled)||* {
+            #statement
+        }
+    }
+}
+
+/// Wraps `method_router` to serve only requests satisfying each of the `required` headers and
+/// guards, checking those of outer blocks first. Rejection handlers and guards take the state
+/// of the block declaring them.
+fn apply_requirements(
+    state: &proc_macro2::TokenStream,
+    required: &[(&Requirement, Option<&Path>)],
+    method_router: proc_macro2::TokenStream,
+) -> proc_macro2::TokenStream {
+    required
+        .iter()
+        .rev()
+        .fold(method_router, |method_router, (requirement, sub_state)| {
+            let header = match requirement {
+                Requirement::Header(header) => header,
+                Requirement::Guard(guard) => {
+                    let guard = guard_tokens(state, *sub_state, guard);
+                    return quote_spanned! {
+                        guard.span() =>
+                        ::axum::routing::any(
+                            ::better_routes::__private::Guarded::new(
+                                ::std::clone::Clone::clone(&route_fallback),
+                            )
+                            .on(::std::option::Option::Some(#guard), #method_router)
+                        )
+                    };
+                }
+            };
+            let name = &header.name;
+            let rejection = header.rejection.as_ref().map(|rejection| {
+                let rejection = match sub_state {
+                    Some(sub_state) => quote_spanned! {
+                        rejection.span() =>
+                        ::better_routes::__private::sub_state::<_, _, #sub_state, #state>(
+                            #rejection
+                        )
+                    },
+                    None => rejection.to_token_stream(),
+                };
+                quote! { .rejection(#rejection) }
+            });
+            quote_spanned! {
+                name.span() =>
+                ::axum::routing::any(
+                    ::better_routes::__private::RequireHeader::new(#name, #method_router)
+                        #rejection
+                )
             }
         })
-        .collect()
 }
 
-#[derive(Debug)]
-enum Segment {
-    Capture(String, Span),
-    Static(String),
+/// A `Guard<state>` checking `guard`, which takes `sub_state` if there is one.
+fn guard_tokens(
+    state: &proc_macro2::TokenStream,
+    sub_state: Option<&Path>,
+    guard: &Path,
+) -> proc_macro2::TokenStream {
+    let sub_state = sub_state.map_or_else(|| state.clone(), ToTokens::to_token_stream);
+    quote_spanned! {
+        guard.span() =>
+        ::better_routes::__private::guard::<_, _, #sub_state, #state>(#guard)
+    }
+}
+
+/// The statement registering `method_router` at `path` on the router `r`.
+fn route_statement(
+    span: Span,
+    trailing_slash: Option<TrailingSlash>,
+    path: &dyn ToTokens,
+    method_router: &proc_macro2::TokenStream,
+) -> proc_macro2::TokenStream {
+    match trailing_slash {
+        None | Some(TrailingSlash::Strict) => quote_spanned! {
+            span =>
+            r = r.route(#path, #method_router);
+        },
+        Some(trailing_slash) => quote_spanned! {
+            span =>
+            r = ::better_routes::__private::route(r, #path, #method_router, #trailing_slash);
+        },
+    }
 }
 
 impl ToTokens for Routes {
     fn to_tokens(&self, tokens: &mut proc_macro2::TokenStream) {
         let Routes {
             name,
-            state,
-            rejection: global_rejection,
-            routes,
+            fallback,
+            fallback_service,
+            layers,
+            entries,
             vis,
+            ..
         } = self;
 
-        let mut routes_fn = Vec::new();
-        routes.iter().for_each(
-            |Route {
-                 path,
-                 segments,
-                 path_struct,
-                 rejection,
-                 get_handler,
-                 post_handler,
-                 put_handler,
-                 patch_handler,
-                 delete_handler,
-             }| {
-                let format_str = format_str_from_path(segments);
-                let captures = captures_from_path(segments);
-                tokens.extend(quote_spanned! {
-                    path.span() =>
-                    #[automatically_derived]
-                    impl ::axum_extra::routing::TypedPath for #path_struct {
-                        const PATH: &'static str = #path;
-                    }
-                });
-                tokens.extend(quote_spanned! {
-                    path.span()=>
-                    #[automatically_derived]
-                    impl ::std::fmt::Display for #path_struct {
-                        #[allow(clippy::unnecessary_to_owned)]
-                        fn fmt(&self, f: &mut ::std::fmt::Formatter<'_>) -> ::std::fmt::Result {
-                            let Self { #(#captures,)* } = self;
-                            write!(
-                                f,
-                                #format_str,
-                                #(
-                                    #captures = ::axum_extra::__private::utf8_percent_encode(
-                                        &#captures.to_string(),
-                                        ::axum_extra::__private::PATH_SEGMENT,
-                                    )
-                                ),*
-                            )
-                        }
-                    }
-                });
-                let (rejection_assoc_type, map_err_rejection) = if rejection.is_some() {
-                    (
-                        rejection_assoc_type(rejection),
-                        map_err_rejection(rejection),
-                    )
-                } else if global_rejection.is_some() {
-                    (
-                        rejection_assoc_type(global_rejection),
-                        map_err_rejection(global_rejection),
-                    )
-                } else {
-                    (
-                        rejection_assoc_type(rejection),
-                        map_err_rejection(rejection),
-                    )
-                };
-                tokens.extend(quote_spanned! {
-                    path_struct.span() =>
-                    #[::axum::async_trait]
-                    #[automatically_derived]
-                    impl<S> ::axum::extract::FromRequestParts<S> for #path_struct
-                    where
-                        S: Send + Sync,
-                    {
-                        type Rejection = #rejection_assoc_type;
-
-                        async fn from_request_parts(
-                            parts: &mut ::axum::http::request::Parts,
-                            state: &S,
-                        ) -> ::std::result::Result<Self, Self::Rejection> {
-                            ::axum::extract::Path::from_request_parts(parts, state)
-                                .await
-                                .map(|path| path.0)
-                                #map_err_rejection
-                        }
-                    }
-                });
-
-                if let Some(get_handler) = get_handler {
-                    routes_fn.push(quote_spanned! {
-                        get_handler.span() =>
-                        r = r.typed_get::<_,_,#path_struct>(#get_handler);
-                    })
-                }
-                if let Some(post_handler) = post_handler {
-                    routes_fn.push(quote_spanned! {
-                        post_handler.span() =>
-                        r = r.typed_post::<_,_,#path_struct>(#post_handler);
-                    })
-                }
-                if let Some(put_handler) = put_handler {
-                    routes_fn.push(quote_spanned! {
-                        put_handler.span() =>
-                        r = r.typed_put::<_,_,#path_struct>(#put_handler);
-                    })
+        let state_ty = self.state_ty();
+        let routes_fn = self.entries_to_tokens(entries, None, &[], &[], tokens);
+        let has_hosts = entries
+            .iter()
+            .any(|entry| matches!(entry.kind, EntryKind::Host(_)));
+        let hosts = has_hosts.then(|| {
+            (
+                quote! {
+                    let mut hosts = ::better_routes::__private::Hosts::<#state_ty>::new();
+                },
+                quote! { r = hosts.route(r, ::std::clone::Clone::clone(&route_fallback)); },
+            )
+        });
+        let (hosts, route_hosts) = hosts.unzip();
+        // Guards that fail and hosts without a block fall through to the fallback, built
+        // before the routes for them. Merged and nested tables get it as an extension, for
+        // their own guards and hosts.
+        let outer_fallback =
+            (fallback.is_some() || fallback_service.is_some()) && has_nested_tables(entries);
+        let shared_fallback = has_guards(entries) || has_hosts || outer_fallback;
+        let route_fallback = shared_fallback.then(|| match (fallback, fallback_service) {
+            (Some(fallback), _) => quote_spanned! {
+                fallback.span() =>
+                #[allow(unused_variables)]
+                let route_fallback = ::axum::routing::any(#fallback);
+            },
+            (None, Some(fallback_service)) => quote_spanned! {
+                fallback_service.span() =>
+                let fallback_service = #fallback_service;
+                #[allow(unused_variables)]
+                let route_fallback = ::axum::routing::any_service(
+                    ::std::clone::Clone::clone(&fallback_service),
+                );
+            },
+            (None, None) => quote! {
+                #[allow(unused_variables)]
+                let route_fallback = ::better_routes::__private::not_found();
+            },
+        });
+        let fallback = match (fallback, fallback_service) {
+            (Some(fallback), _) => Some(quote_spanned! {
+                fallback.span() =>
+                r = r.fallback(#fallback);
+            }),
+            (None, Some(fallback_service)) if shared_fallback => Some(quote_spanned! {
+                fallback_service.span() =>
+                r = r.fallback_service(fallback_service);
+            }),
+            (None, Some(fallback_service)) => Some(quote_spanned! {
+                fallback_service.span() =>
+                r = r.fallback_service(#fallback_service);
+            }),
+            (None, None) => None,
+        };
+        let outer_fallback = outer_fallback.then(|| {
+            quote! {
+                r = r.layer(::axum::Extension(::better_routes::__private::OuterFallback::<#state_ty>(
+                    ::std::clone::Clone::clone(&route_fallback),
+                )));
+            }
+        });
+        let layers = layers.iter().map(router_layer);
+        tokens.extend(quote_spanned! {
+            name.span() =>
+            #vis struct #name;
+            #[automatically_derived]
+            impl ::better_routes::__private::RouteTable<#state_ty> for #name {}
+            #[automatically_derived]
+            #[allow(unused_attributes)]
+            #[allow(unused_mut)]
+            #[allow(clippy::let_and_return)]
+            impl #name {
+                #vis fn routes() -> ::axum::Router<#state_ty> {
+                    let mut r = ::axum::Router::new();
+                    #route_fallback
+                    #hosts
+                    #(#routes_fn)*
+                    #route_hosts
+                    // The state of the routes is inferred from their handlers, so handlers
+                    // taking another state fail here with the two router types.
+                    let mut r: ::axum::Router<#state_ty> = r;
+                    r = ::better_routes::__private::registered::<Self, _>(r);
+                    #outer_fallback
+                    #fallback
+                    #(#layers)*
+                    r
                 }
-                if let Some(patch_handler) = patch_handler {
-                    routes_fn.push(quote_spanned! {
-                        patch_handler.span() =>
-                        r = r.typed_patch::<_,_,#path_struct>(#patch_handler);
-                    })
+            }
+        })
+    }
+}
+
+/// Builds the `MethodRouter<state>` of a route. Handlers of routes with a `sub_state` are
+/// checked against it and wrapped to extract it from `state`, except for the global
+/// `method_not_allowed` and `not_acceptable` handlers, which always take `state`.
+fn method_router(
+    state: &proc_macro2::TokenStream,
+    sub_state: Option<&Path>,
+    path_struct: &proc_macro2::TokenStream,
+    Endpoint {
+        handlers,
+        any_handler,
+        method_not_allowed,
+        not_acceptable,
+        layers,
+        ..
+    }: &Endpoint,
+    global_method_not_allowed: &Option<Path>,
+    global_not_acceptable: &Option<Path>,
+) -> proc_macro2::TokenStream {
+    let typed = |handler: &Path| match sub_state {
+        Some(sub_state) => quote_spanned! {
+            handler.span() =>
+            ::better_routes::__private::sub_state::<_, _, #sub_state, #state>(
+                ::better_routes::__private::typed::<_, _, #path_struct>(#handler),
+            )
+        },
+        None => quote_spanned! {
+            handler.span() =>
+            ::better_routes::__private::typed::<_, _, #path_struct>(#handler)
+        },
+    };
+    let mut standard = Vec::new();
+    let mut extensions = Vec::new();
+    // Registers `handler` for `methods`, with `guard` putting each statement behind `#[cfg]`s.
+    let mut register =
+        |methods: &[&Method],
+         handler: &proc_macro2::TokenStream,
+         guard: &dyn Fn(proc_macro2::TokenStream) -> proc_macro2::TokenStream| {
+            let filters = methods
+                .iter()
+                .filter_map(|method| match method.filter() {
+                    Some(filter) => Some(quote! { ::axum::routing::MethodFilter::#filter }),
+                    None => {
+                        let name = method.name();
+                        extensions.push(guard(quote! { fallback = fallback.on(#name, #handler); }));
+                        None
+                    }
+                })
+                .collect::<Vec<_>>();
+            if let Some((first, rest)) = filters.split_first() {
+                standard.push(guard(quote! {
+                    method_router = method_router.on(#first #(.or(#rest))*, #handler);
+                }));
+            }
+        };
+    // Handlers of a method with guards are tried in order, in a handler falling through from
+    // one to the next when its guard fails.
+    let guarded = |handlers: &[&MethodHandler]| {
+        let on = handlers.iter().map(|handler| {
+            let guard = match &handler.guard {
+                Some(guard) => {
+                    let guard = guard_tokens(state, sub_state, guard);
+                    quote! { ::std::option::Option::Some(#guard) }
                 }
-                if let Some(delete_handler) = delete_handler {
-                    routes_fn.push(quote_spanned! {
-                        delete_handler.span() =>
-                        r = r.typed_delete::<_,_,#path_struct>(#delete_handler);
-                    })
+                None => quote! { ::std::option::Option::None },
+            };
+            let typed = typed(&handler.handler);
+            cfg_statement(
+                cfgs(&handler.attrs),
+                quote! { guarded = guarded.on(#guard, #typed); },
+            )
+        });
+        quote! {
+            {
+                let mut guarded = ::better_routes::__private::Guarded::new(
+                    ::std::clone::Clone::clone(&route_fallback),
+                );
+                #(#on)*
+                guarded
+            }
+        }
+    };
+    let plain = handlers
+        .iter()
+        .filter(|handler| handler.content_type.is_none() && handler.accept.is_none());
+    let guarded_methods = plain
+        .clone()
+        .filter(|handler| handler.guard.is_some())
+        .flat_map(|handler| handler.methods.iter().map(Method::name))
+        .collect::<Vec<_>>();
+    for MethodHandler {
+        attrs,
+        methods,
+        handler,
+        ..
+    } in plain.clone()
+    {
+        let cfgs = cfgs(attrs);
+        let methods = methods
+            .iter()
+            .filter(|method| !guarded_methods.contains(&method.name()))
+            .collect::<Vec<_>>();
+        register(&methods, &typed(handler), &|statement| {
+            cfg_statement(cfgs.clone(), statement)
+        });
+    }
+    for (method, handlers) in by_method(plain) {
+        if guarded_methods.contains(&method.name()) {
+            register(&[method], &guarded(&handlers), &|statement| {
+                if_any_enabled(&handlers, statement)
+            });
+        }
+    }
+    // Handlers for media types are registered together for each of their methods, in a
+    // handler choosing one of them from the `Content-Type` header of the request, then from its
+    // `Accept` header.
+    let not_acceptable = match (not_acceptable, global_not_acceptable) {
+        (Some(not_acceptable), _) => {
+            let not_acceptable = typed(not_acceptable);
+            Some(quote! { negotiate = negotiate.not_acceptable(#not_acceptable); })
+        }
+        (None, Some(not_acceptable)) => Some(quote_spanned! {
+            not_acceptable.span() =>
+            negotiate = negotiate.not_acceptable(#not_acceptable);
+        }),
+        (None, None) => None,
+    };
+    // The statements adding `handlers`, which share a media type, to `negotiate`.
+    let on = |media: &str, handlers: &[&MethodHandler]| {
+        if handlers.iter().any(|handler| handler.guard.is_some()) {
+            let guarded = guarded(handlers);
+            return vec![if_any_enabled(
+                handlers,
+                quote! { negotiate = negotiate.on(#media, #guarded); },
+            )];
+        }
+        handlers
+            .iter()
+            .map(|handler| {
+                let typed = typed(&handler.handler);
+                cfg_statement(
+                    cfgs(&handler.attrs),
+                    quote! { negotiate = negotiate.on(#media, #typed); },
+                )
+            })
+            .collect::<Vec<_>>()
+    };
+    let by_accept = |handlers: &[&MethodHandler]| {
+        let on = by_media(handlers, |handler| handler.accept.as_ref())
+            .into_iter()
+            .flat_map(|(media, handlers)| on(media, &handlers));
+        quote! {
+            {
+                let mut negotiate = ::better_routes::__private::Negotiate::accept();
+                #(#on)*
+                #not_acceptable
+                negotiate
+            }
+        }
+    };
+    let by_content_type = |handlers: &[&MethodHandler]| {
+        let on = by_media(handlers, |handler| handler.content_type.as_ref())
+            .into_iter()
+            .flat_map(|(media, handlers)| {
+                let (accepting, plain): (Vec<_>, Vec<_>) = handlers
+                    .into_iter()
+                    .partition(|handler| handler.accept.is_some());
+                let mut on = on(media, &plain);
+                if !accepting.is_empty() {
+                    let by_accept = by_accept(&accepting);
+                    on.push(if_any_enabled(
+                        &accepting,
+                        quote! { negotiate = negotiate.on(#media, #by_accept); },
+                    ));
                 }
-            },
-        );
-        if let Some(state) = state {
-            tokens.extend(quote_spanned! {
-                name.span() =>
-                #vis struct #name;
-                #[automatically_derived]
-                #[allow(unused_mut)]
-                #[allow(clippy::let_and_return)]
-                impl #name {
-                    #vis fn routes() -> ::axum::Router<#state> {
-                        let mut r = ::axum::Router::new();
-                        #(#routes_fn)*
-                        r
-                    }
+                on
+            });
+        quote! {
+            {
+                let mut negotiate = ::better_routes::__private::Negotiate::content_type();
+                #(#on)*
+                negotiate
+            }
+        }
+    };
+    let negotiated = by_method(
+        handlers
+            .iter()
+            .filter(|handler| handler.content_type.is_some() || handler.accept.is_some()),
+    );
+    for (method, handlers) in negotiated {
+        // Only handlers behind `#[cfg]`s can be chosen by `Content-Type` along ones that aren't.
+        let (by_type, accepting): (Vec<_>, Vec<_>) = handlers
+            .into_iter()
+            .partition(|handler| handler.content_type.is_some());
+        if !by_type.is_empty() {
+            register(&[method], &by_content_type(&by_type), &|statement| {
+                if_any_enabled(&by_type, statement)
+            });
+        }
+        if !accepting.is_empty() {
+            register(&[method], &by_accept(&accepting), &|statement| {
+                if_any_enabled(&accepting, statement)
+            });
+        }
+    }
+    let method_not_allowed = match (any_handler, method_not_allowed, global_method_not_allowed) {
+        (Some(_), _, _) => None,
+        (None, Some(method_not_allowed), _) => {
+            let method_not_allowed = typed(method_not_allowed);
+            Some(quote! { fallback = fallback.method_not_allowed(#method_not_allowed); })
+        }
+        (None, None, Some(method_not_allowed)) => Some(quote_spanned! {
+            method_not_allowed.span() =>
+            fallback = fallback.method_not_allowed(#method_not_allowed);
+        }),
+        (None, None, None) => None,
+    };
+    let any_handler = any_handler.as_ref().map(|any_handler| {
+        let any_handler = typed(any_handler);
+        quote! { fallback = fallback.any(#any_handler); }
+    });
+    // `route_layer` panics on a method router without standard methods, which can depend on
+    // the `#[cfg]`s of their handlers.
+    let standard_handlers = handlers
+        .iter()
+        .filter(|handler| {
+            handler
+                .methods
+                .iter()
+                .any(|method| method.filter().is_some())
+        })
+        .collect::<Vec<_>>();
+    let unconditional = standard_handlers
+        .iter()
+        .any(|handler| cfgs(&handler.attrs).next().is_none());
+    let enabled = standard_handlers
+        .iter()
+        .map(|handler| cfg_enabled(&handler.attrs))
+        .collect::<Vec<_>>();
+    let route_layer = |ident: &Ident| {
+        let route_layer = quote! { method_router = method_router.route_layer(#ident); };
+        if unconditional {
+            Some(route_layer)
+        } else if enabled.is_empty() {
+            None
+        } else {
+            Some(quote! {
+                if #(#enabled)||* {
+                    #route_layer
                 }
             })
+        }
+    };
+    let layer_idents = (0..layers.len())
+        .map(|i| format_ident!("layer_{}", i))
+        .collect::<Vec<_>>();
+    let layer_bindings = layers.iter().zip(&layer_idents).map(
+        |(Layer { layer, .. }, ident)| quote_spanned! { layer.span() => let #ident = #layer; },
+    );
+    // Routes with a fallback handle every method themselves, including the `Allow` header
+    // of their 405 responses, so axum's own method fallback is skipped through `any`.
+    let method_router =
+        if extensions.is_empty() && any_handler.is_none() && method_not_allowed.is_none() {
+            let layers = layers.iter().zip(&layer_idents).map(|(layer, ident)| {
+                if layer.route_only {
+                    route_layer(ident)
+                } else {
+                    Some(quote! { method_router = method_router.layer(#ident); })
+                }
+            });
+            quote! {
+                let mut method_router = ::axum::routing::MethodRouter::new();
+                #(#standard)*
+                #(#layers)*
+            }
         } else {
-            tokens.extend(quote_spanned! {
-                name.span() =>
-                #vis struct #name;
-                #[automatically_derived]
-                #[allow(unused_mut)]
-                #[allow(clippy::let_and_return)]
-                impl #name {
-                    #vis fn routes() -> ::axum::Router {
-                        let mut r = ::axum::Router::new();
-                        #(#routes_fn)*
-                        r
+            // The fallback layers its own endpoints, so the standard methods only get
+            // `route_layer`s, which don't reach the fallback.
+            let allow = allow_header(handlers);
+            let fallback_layers = layers.iter().zip(&layer_idents).map(|(layer, ident)| {
+                if layer.route_only {
+                    quote! {
+                        fallback = fallback.route_layer(::std::clone::Clone::clone(&#ident));
                     }
+                } else {
+                    quote! { fallback = fallback.layer(::std::clone::Clone::clone(&#ident)); }
                 }
-            })
+            });
+            let standard_layers = layer_idents.iter().map(route_layer);
+            quote! {
+                let mut fallback = ::better_routes::__private::MethodFallback::new(#allow);
+                #(#extensions)*
+                #any_handler
+                #method_not_allowed
+                #(#fallback_layers)*
+                let mut method_router = ::axum::routing::any(fallback);
+                #(#standard)*
+                #(#standard_layers)*
+            }
+        };
+    quote! {
+        {
+            #(#layer_bindings)*
+            #method_router
+            method_router
+        }
+    }
+}
+
+/// The statement applying `layer` to the router `r` of a table, group or host block. Routes
+/// can all be behind `#[cfg]`s, or at the top level registered with `#[handler]`, so whether
+/// there is one for `route_layer`, which panics otherwise, is only known at runtime.
+fn router_layer(Layer { route_only, layer }: &Layer) -> proc_macro2::TokenStream {
+    if *route_only {
+        quote_spanned! {
+            layer.span() =>
+            if r.has_routes() {
+                r = r.route_layer(#layer);
+            }
+        }
+    } else {
+        quote_spanned! { layer.span() => r = r.layer(#layer); }
+    }
+}
+
+/// Value of the `Allow` header, listed the same way axum does for its own 405 responses. With
+/// handlers behind `#[cfg]` it is built once at runtime, from the handlers compiled in.
+fn allow_header(handlers: &[MethodHandler]) -> proc_macro2::TokenStream {
+    if handlers
+        .iter()
+        .all(|handler| cfgs(&handler.attrs).next().is_none())
+    {
+        let mut names = Vec::new();
+        for name in handlers
+            .iter()
+            .flat_map(|handler| &handler.methods)
+            .map(Method::name)
+        {
+            if !names.contains(&name) {
+                names.push(name);
+            }
+        }
+        if names.iter().any(|name| name == "GET") && !names.iter().any(|name| name == "HEAD") {
+            names.push("HEAD".to_owned());
+        }
+        let allow = names.join(",");
+        return quote! { #allow };
+    }
+    let methods = handlers.iter().flat_map(|handler| {
+        let enabled = cfg_enabled(&handler.attrs);
+        handler.methods.iter().map(move |method| {
+            let name = method.name();
+            quote! { (#enabled, #name) }
+        })
+    });
+    quote! {
+        {
+            static ALLOW: ::std::sync::OnceLock<::std::string::String> =
+                ::std::sync::OnceLock::new();
+            ALLOW
+                .get_or_init(|| ::better_routes::__private::allow_header(&[#(#methods),*]))
+                .as_str()
         }
     }
 }
@@ -363,7 +2686,7 @@ fn format_str_from_path(segments: &[Segment]) -> String {
     segments
         .iter()
         .map(|segment| match segment {
-            Segment::Capture(capture, _) => format!("{{{capture}}}"),
+            Segment::Capture(capture) => format!("{{{}}}", capture.name),
             Segment::Static(segment) => segment.to_owned(),
         })
         .collect::<Vec<_>>()
@@ -374,35 +2697,281 @@ fn captures_from_path(segments: &[Segment]) -> Vec<syn::Ident> {
     segments
         .iter()
         .filter_map(|segment| match segment {
-            Segment::Capture(capture, span) => Some(format_ident!("{}", capture, span = *span)),
+            Segment::Capture(capture) => {
+                Some(format_ident!("{}", capture.name, span = capture.span))
+            }
             Segment::Static(_) => None,
         })
         .collect::<Vec<_>>()
 }
 
-fn path_rejection() -> proc_macro2::TokenStream {
+/// Emits the `TypedPath`, `Display` and `FromRequestParts` impls of a path struct, shared by
+/// `routes!` and `#[derive(Path)]`. The captures of tuple structs are their fields in order,
+/// the ones of the `host` first. The impls are all behind the `#[cfg]`s among `attrs`, and the
+/// `#[doc]`s document the `TypedPath` one. The struct can only have fields other than the
+/// captures with `other_fields`, like the `PhantomData` of a generic struct.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn path_impls(
+    path: &LitStr,
+    segments: &[Segment],
+    host: Option<&Host>,
+    path_struct: &impl ToTokens,
+    generics: &Generics,
+    tuple: bool,
+    other_fields: bool,
+    rejection: &Option<Path>,
+    attrs: &[&Attribute],
+) -> proc_macro2::TokenStream {
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    // The state parameter of `FromRequestParts`, renamed if the path struct has an `S`.
+    let state = if generics.type_params().any(|param| param.ident == "S") {
+        quote! { __S }
+    } else {
+        quote! { S }
+    };
+    let mut from_request_parts_generics = generics.clone();
+    from_request_parts_generics
+        .params
+        .push(parse_quote! { #state });
+    let predicates = &mut from_request_parts_generics.make_where_clause().predicates;
+    predicates.push(parse_quote! { #state: Send + Sync });
+    if !generics.params.is_empty() {
+        predicates.push(parse_quote! { Self: ::serde::de::DeserializeOwned + Send });
+    }
+    let (from_request_parts_impl_generics, _, from_request_parts_where_clause) =
+        from_request_parts_generics.split_for_impl();
+    let cfgs = attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("cfg"))
+        .collect::<Vec<_>>();
+    let host_segments = host
+        .map(|host| host.segments.as_slice())
+        .unwrap_or_default();
+    let all_segments = host_segments
+        .iter()
+        .chain(segments)
+        .cloned()
+        .collect::<Vec<_>>();
+    let format_str = format_str_from_path(segments);
+    let captures = captures_from_path(segments);
+    let all_captures = captures_from_path(&all_segments);
+    // With `{:#}`, the host is written first, as in `//acme.example.com/users/1`.
+    let write_host = host.map(|_| {
+        let format_str = format!(
+            "//{}",
+            host_segments
+                .iter()
+                .map(|segment| match segment {
+                    Segment::Capture(capture) => format!("{{{}}}", capture.name),
+                    Segment::Static(label) => label.to_owned(),
+                })
+                .collect::<Vec<_>>()
+                .join(".")
+        );
+        let captures = captures_from_path(host_segments);
+        quote! {
+            if f.alternate() {
+                write!(f, #format_str, #(#captures = #captures),*)?;
+            }
+        }
+    });
+    // Typed captures must have the type of their field.
+    let (typed_captures, capture_types): (Vec<_>, Vec<_>) = all_segments
+        .iter()
+        .filter_map(|segment| match segment {
+            Segment::Capture(capture) => capture
+                .ty
+                .as_ref()
+                .map(|ty| (format_ident!("{}", capture.name, span = capture.span), ty)),
+            Segment::Static(_) => None,
+        })
+        .unzip();
+    let fields = all_captures.iter().enumerate().map(|(index, capture)| {
+        if tuple {
+            let index = syn::Index::from(index);
+            quote! { #index: #capture }
+        } else {
+            quote! { #capture }
+        }
+    });
+    let constrained = segments
+        .iter()
+        .filter_map(|segment| match segment {
+            Segment::Capture(capture) => capture.constraint.as_ref().map(|c| (capture, c)),
+            Segment::Static(_) => None,
+        })
+        .collect::<Vec<_>>();
+    let host_captured = host.is_some_and(Host::has_captures);
+    let captured = !constrained.is_empty() || host_captured;
+    let rejection_assoc_type = rejection_assoc_type(rejection, captured, &state);
+    let map_err_rejection = map_err_rejection(rejection, captured, &state);
+    let check_constraints = captured.then(|| {
+        let invalid_capture = match rejection {
+            Some(rejection) => quote! {
+                <#rejection as ::std::convert::From<::better_routes::InvalidCapture>>::from
+            },
+            None => quote! { ::better_routes::CaptureRejection::Invalid },
+        };
+        let checks = constrained.iter().map(|(capture, constraint)| {
+            let name = &capture.name;
+            let matches = match &capture.range {
+                Some(range) => quote! {
+                    value.parse::<i128>().is_ok_and(|value| (#range).contains(&value))
+                },
+                None => {
+                    let regex = format!("^(?:{constraint})$");
+                    quote! {
+                        {
+                            static REGEX: ::std::sync::OnceLock<::better_routes::__private::Regex> =
+                                ::std::sync::OnceLock::new();
+                            REGEX
+                                .get_or_init(|| {
+                                    ::better_routes::__private::Regex::new(#regex).unwrap()
+                                })
+                                .is_match(value)
+                        }
+                    }
+                }
+            };
+            quote! {
+                if let Some((_, value)) = captures.iter().find(|(name, _)| name == #name) {
+                    if !#matches {
+                        return Err(#invalid_capture(
+                            ::better_routes::__private::invalid_capture(#name, #constraint, value),
+                        ));
+                    }
+                }
+            }
+        });
+        quote! {
+            let ::axum::extract::Path(captures) = <::axum::extract::Path<
+                ::std::vec::Vec<(::std::string::String, ::std::string::String)>,
+            > as ::axum::extract::FromRequestParts<#state>>::from_request_parts(parts, state)
+                .await
+                #map_err_rejection?;
+            #(#checks)*
+        }
+    });
+    let extract = match host {
+        Some(host) if host_captured => {
+            let pattern = &host.pattern;
+            let failed_to_deserialize = match rejection {
+                Some(rejection) => quote! {
+                    <#rejection as ::std::convert::From<
+                        ::better_routes::FailedToDeserializeCaptures,
+                    >>::from
+                },
+                None => quote! { ::better_routes::CaptureRejection::Deserialize },
+            };
+            quote! {
+                ::better_routes::__private::with_host_captures(parts, #pattern, captures)
+                    .map_err(#failed_to_deserialize)
+            }
+        }
+        _ => quote! {
+            ::axum::extract::Path::from_request_parts(parts, state)
+                .await
+                .map(|path| path.0)
+                #map_err_rejection
+        },
+    };
+    let rest = other_fields.then(|| quote! { .. });
+    let path_struct = path_struct.to_token_stream();
+    let path_impls = quote_spanned! {
+        path.span() =>
+        #(#attrs)*
+        #[automatically_derived]
+        #[allow(deprecated)]
+        impl #impl_generics ::axum_extra::routing::TypedPath for #path_struct #ty_generics
+        #where_clause
+        {
+            const PATH: &'static str = #path;
+        }
+
+        #(#cfgs)*
+        #[automatically_derived]
+        #[allow(deprecated)]
+        impl #impl_generics ::std::fmt::Display for #path_struct #ty_generics #where_clause {
+            #[allow(clippy::unnecessary_to_owned)]
+            fn fmt(&self, f: &mut ::std::fmt::Formatter<'_>) -> ::std::fmt::Result {
+                let Self { #(#fields,)* #rest } = self;
+                #(let _: &#capture_types = #typed_captures;)*
+                #write_host
+                write!(
+                    f,
+                    #format_str,
+                    #(
+                        #captures = ::better_routes::__private::utf8_percent_encode(
+                            &#captures.to_string(),
+                            ::better_routes::__private::PATH_SEGMENT,
+                        )
+                    ),*
+                )
+            }
+        }
+    };
+    let from_request_parts_impl = quote_spanned! {
+        path_struct.span() =>
+        #(#cfgs)*
+        #[::axum::async_trait]
+        #[automatically_derived]
+        #[allow(deprecated)]
+        impl #from_request_parts_impl_generics ::axum::extract::FromRequestParts<#state>
+            for #path_struct #ty_generics
+        #from_request_parts_where_clause
+        {
+            type Rejection = #rejection_assoc_type;
+
+            async fn from_request_parts(
+                parts: &mut ::axum::http::request::Parts,
+                state: &#state,
+            ) -> ::std::result::Result<Self, Self::Rejection> {
+                #check_constraints
+                #extract
+            }
+        }
+    };
+    quote! {
+        #path_impls
+        #from_request_parts_impl
+    }
+}
+
+fn path_rejection(state: &proc_macro2::TokenStream) -> proc_macro2::TokenStream {
     quote! {
-        <::axum::extract::Path<Self> as ::axum::extract::FromRequestParts<S>>::Rejection
+        <::axum::extract::Path<Self> as ::axum::extract::FromRequestParts<#state>>::Rejection
     }
 }
 
-fn rejection_assoc_type(rejection: &Option<syn::Path>) -> proc_macro2::TokenStream {
+/// Path structs with constrained or host captures reject with `CaptureRejection` by default,
+/// since their captures can also be invalid or fail to deserialize with the host ones.
+fn rejection_assoc_type(
+    rejection: &Option<syn::Path>,
+    captured: bool,
+    state: &proc_macro2::TokenStream,
+) -> proc_macro2::TokenStream {
     match rejection {
         Some(rejection) => quote! { #rejection },
-        None => path_rejection(),
+        None if captured => quote! { ::better_routes::CaptureRejection },
+        None => path_rejection(state),
     }
 }
 
-fn map_err_rejection(rejection: &Option<syn::Path>) -> proc_macro2::TokenStream {
-    rejection
-        .as_ref()
-        .map(|rejection| {
-            let path_rejection = path_rejection();
+fn map_err_rejection(
+    rejection: &Option<syn::Path>,
+    captured: bool,
+    state: &proc_macro2::TokenStream,
+) -> proc_macro2::TokenStream {
+    match rejection {
+        Some(rejection) => {
+            let path_rejection = path_rejection(state);
             quote! {
                 .map_err(|rejection| {
                     <#rejection as ::std::convert::From<#path_rejection>>::from(rejection)
                 })
             }
-        })
-        .unwrap_or_default()
+        }
+        None if captured => quote! { .map_err(::better_routes::CaptureRejection::Path) },
+        None => quote! {},
+    }
 }
@@ -0,0 +1,1096 @@
+//! Runtime support for the code generated by [`routes!`](crate::routes). Not public API.
+
+use std::any::{Any, TypeId};
+use std::convert::Infallible;
+use std::fmt::Display;
+use std::future::Future;
+use std::marker::PhantomData;
+use std::pin::Pin;
+use std::sync::Arc;
+
+use axum::extract::{FromRef, OriginalUri, Path, Request, State};
+use axum::handler::Handler;
+use axum::http::request::Parts;
+use axum::http::{header, HeaderMap, HeaderName, HeaderValue, Method, StatusCode, Uri};
+use axum::response::{IntoResponse, Redirect, Response};
+use axum::routing::{any, MethodFilter, MethodRouter, Route};
+use axum::Router;
+use axum_extra::routing::{SecondElementIs, TypedPath};
+use percent_encoding::{AsciiSet, CONTROLS};
+use tower_layer::Layer;
+use tower_service::Service;
+
+use serde::de::DeserializeOwned;
+
+use crate::{AllowedMethods, FailedToDeserializeCaptures, InvalidCapture};
+
+pub use inventory;
+pub use percent_encoding::utf8_percent_encode;
+pub use regex::Regex;
+
+/// Checks that the first extractor of `handler` is the path struct `P`. The state of the
+/// handler is left to be inferred from the router it is registered on.
+pub fn typed<H, T, P>(handler: H) -> Typed<H, T, P>
+where
+    T: SecondElementIs<P> + 'static,
+    P: TypedPath,
+{
+    Typed {
+        handler,
+        _marker: PhantomData,
+    }
+}
+
+pub struct Typed<H, T, P> {
+    handler: H,
+    _marker: PhantomData<fn() -> (T, P)>,
+}
+
+impl<H, T, P> Clone for Typed<H, T, P>
+where
+    H: Clone,
+{
+    fn clone(&self) -> Self {
+        Self {
+            handler: self.handler.clone(),
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<H, T, P, S> Handler<T, S> for Typed<H, T, P>
+where
+    H: Handler<T, S>,
+    T: 'static,
+    P: 'static,
+{
+    type Future = H::Future;
+
+    fn call(self, req: Request, state: S) -> Self::Future {
+        self.handler.call(req, state)
+    }
+}
+
+/// Wraps a handler taking the state `Sub` of its group or route so it can be registered on a
+/// router with state `S`, extracting `Sub` from `S` with [`FromRef`] on every request.
+pub fn sub_state<H, T, Sub, S>(handler: H) -> SubState<H, T, Sub>
+where
+    H: Handler<T, Sub>,
+    Sub: FromRef<S>,
+{
+    SubState {
+        handler,
+        _marker: PhantomData,
+    }
+}
+
+pub struct SubState<H, T, Sub> {
+    handler: H,
+    _marker: PhantomData<fn() -> (T, Sub)>,
+}
+
+impl<H, T, Sub> Clone for SubState<H, T, Sub>
+where
+    H: Clone,
+{
+    fn clone(&self) -> Self {
+        Self {
+            handler: self.handler.clone(),
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<H, T, Sub, S> Handler<(), S> for SubState<H, T, Sub>
+where
+    H: Handler<T, Sub>,
+    T: 'static,
+    Sub: FromRef<S> + 'static,
+{
+    type Future = H::Future;
+
+    fn call(self, req: Request, state: S) -> Self::Future {
+        self.handler.call(req, Sub::from_ref(&state))
+    }
+}
+
+/// Value of the `Allow` header for routes with handlers behind `#[cfg]`, listing the `methods`
+/// compiled in once each, plus `HEAD` when `GET` is.
+pub fn allow_header(methods: &[(bool, &str)]) -> String {
+    let mut names = Vec::new();
+    for (_, name) in methods.iter().filter(|(enabled, _)| *enabled) {
+        if !names.contains(name) {
+            names.push(*name);
+        }
+    }
+    if names.contains(&"GET") && !names.contains(&"HEAD") {
+        names.push("HEAD");
+    }
+    names.join(",")
+}
+
+/// The rejection for a capture `name` whose `value` doesn't match `constraint`.
+pub fn invalid_capture(
+    name: &'static str,
+    constraint: &'static str,
+    value: &str,
+) -> InvalidCapture {
+    InvalidCapture::new(name, constraint, value.to_owned())
+}
+
+/// How a route serves its path with or without a trailing slash, the other way than it is
+/// declared.
+#[derive(Clone, Copy)]
+pub enum TrailingSlash {
+    Strict,
+    Redirect,
+    Both,
+}
+
+/// Adds `method_router` to `router` at `path`, and at `path` with or without its trailing slash
+/// as `trailing_slash` says. The root and paths ending with a wildcard only have one form.
+pub fn route<S, R>(
+    router: R,
+    path: &str,
+    method_router: MethodRouter<S>,
+    trailing_slash: TrailingSlash,
+) -> R
+where
+    S: Clone + Send + Sync + 'static,
+    R: AddRoute<S>,
+{
+    let other = match path.strip_suffix('/') {
+        Some("") => None,
+        Some(other) => Some(other.to_owned()),
+        None if path
+            .rsplit('/')
+            .next()
+            .is_some_and(|last| last.starts_with('*')) =>
+        {
+            None
+        }
+        None => Some(format!("{path}/")),
+    };
+    let Some(other) = other else {
+        return router.add_route(path, method_router);
+    };
+    match trailing_slash {
+        TrailingSlash::Strict => router.add_route(path, method_router),
+        TrailingSlash::Both => router
+            .add_route(path, method_router.clone())
+            .add_route(&other, method_router),
+        TrailingSlash::Redirect => {
+            let slash = path.ends_with('/');
+            router
+                .add_route(path, method_router)
+                .add_route(&other, any(move |uri: OriginalUri| redirect(uri, slash)))
+        }
+    }
+}
+
+/// A `Router` or the [`HostRoutes`] of a `host` block, which [`route`] adds routes to.
+pub trait AddRoute<S> {
+    fn add_route(self, path: &str, method_router: MethodRouter<S>) -> Self;
+}
+
+impl<S> AddRoute<S> for Router<S>
+where
+    S: Clone + Send + Sync + 'static,
+{
+    fn add_route(self, path: &str, method_router: MethodRouter<S>) -> Self {
+        self.route(path, method_router)
+    }
+}
+
+impl<S> AddRoute<S> for HostRoutes<S>
+where
+    S: Clone + Send + Sync + 'static,
+{
+    fn add_route(self, path: &str, method_router: MethodRouter<S>) -> Self {
+        self.route(path, method_router)
+    }
+}
+
+/// The routes of a `host` block, with the same API as the `Router` of a group. They are only
+/// added to the router by [`Hosts`], since blocks for different hosts can share paths.
+pub struct HostRoutes<S> {
+    routes: Vec<(String, MethodRouter<S>)>,
+}
+
+impl<S> Default for HostRoutes<S> {
+    fn default() -> Self {
+        Self { routes: Vec::new() }
+    }
+}
+
+impl<S> HostRoutes<S>
+where
+    S: Clone + Send + Sync + 'static,
+{
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds `method_router` at `path`, merging it with the one already there like
+    /// `Router::route`.
+    pub fn route(mut self, path: &str, method_router: MethodRouter<S>) -> Self {
+        match self.routes.iter_mut().find(|(other, _)| other == path) {
+            Some((_, existing)) => {
+                *existing = std::mem::take(existing).merge(method_router);
+            }
+            None => self.routes.push((path.to_owned(), method_router)),
+        }
+        self
+    }
+
+    pub fn has_routes(&self) -> bool {
+        !self.routes.is_empty()
+    }
+
+    pub fn merge(mut self, other: Self) -> Self {
+        for (path, method_router) in other.routes {
+            self = self.route(&path, method_router);
+        }
+        self
+    }
+
+    /// Applies `layer` to every route, like `Router::layer`, so to their `405` responses too.
+    pub fn layer<L>(mut self, layer: L) -> Self
+    where
+        L: Layer<Route> + Clone + Send + 'static,
+        L::Service: Service<Request> + Clone + Send + 'static,
+        <L::Service as Service<Request>>::Response: IntoResponse + 'static,
+        <L::Service as Service<Request>>::Error: Into<Infallible> + 'static,
+        <L::Service as Service<Request>>::Future: Send + 'static,
+    {
+        for (_, method_router) in &mut self.routes {
+            *method_router = std::mem::take(method_router).layer(layer.clone());
+        }
+        self
+    }
+
+    /// Same as [`layer`](Self::layer), since a host block has no fallback, like
+    /// `Router::route_layer`.
+    pub fn route_layer<L>(self, layer: L) -> Self
+    where
+        L: Layer<Route> + Clone + Send + 'static,
+        L::Service: Service<Request> + Clone + Send + 'static,
+        <L::Service as Service<Request>>::Response: IntoResponse + 'static,
+        <L::Service as Service<Request>>::Error: Into<Infallible> + 'static,
+        <L::Service as Service<Request>>::Future: Send + 'static,
+    {
+        self.layer(layer)
+    }
+}
+
+/// The routes of every `host` block of a table, by path. Each path is served by the routes of
+/// the first block whose host matches the request, and by the fallback of the table for other
+/// hosts.
+pub struct Hosts<S> {
+    routes: Vec<(String, HostEndpoints<S>)>,
+}
+
+/// The method routers of a path, each with the host of its block.
+type HostEndpoints<S> = Vec<(&'static str, MethodRouter<S>)>;
+
+impl<S> Default for Hosts<S> {
+    fn default() -> Self {
+        Self { routes: Vec::new() }
+    }
+}
+
+impl<S> Hosts<S>
+where
+    S: Clone + Send + Sync + 'static,
+{
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds the `routes` of the block for `host`, a pattern such as `":tenant.example.com"`.
+    pub fn host(&mut self, host: &'static str, routes: HostRoutes<S>) {
+        for (path, method_router) in routes.routes {
+            match self.routes.iter_mut().find(|(other, _)| *other == path) {
+                Some((_, hosts)) => hosts.push((host, method_router)),
+                None => self.routes.push((path, vec![(host, method_router)])),
+            }
+        }
+    }
+
+    /// Adds every path of the `host` blocks to `router`, answered by `fallback` for requests
+    /// without a matching block.
+    pub fn route(self, router: Router<S>, fallback: MethodRouter<S>) -> Router<S> {
+        self.routes
+            .into_iter()
+            .fold(router, |router, (path, hosts)| {
+                let hosts = Arc::new(hosts);
+                let fallback = fallback.clone();
+                router.route(
+                    &path,
+                    any(move |State(state): State<S>, req: Request| {
+                        let hosts = Arc::clone(&hosts);
+                        let fallback = fallback.clone();
+                        async move {
+                            let endpoint =
+                                request_host(req.headers(), req.uri()).and_then(|host| {
+                                    hosts
+                                        .iter()
+                                        .find(|(pattern, _)| match_host(pattern, host).is_some())
+                                        .map(|(_, endpoint)| endpoint.clone())
+                                });
+                            match endpoint {
+                                Some(endpoint) => Handler::call(endpoint, req, state).await,
+                                None => Handler::call(fallback, req, state).await,
+                            }
+                        }
+                    }),
+                )
+            })
+    }
+}
+
+/// The host of a request, from its `Host` header or its URI with HTTP/2, without the port.
+fn request_host<'a>(headers: &'a HeaderMap, uri: &'a Uri) -> Option<&'a str> {
+    let host = headers
+        .get(header::HOST)
+        .and_then(|host| host.to_str().ok())
+        .or_else(|| uri.host())?;
+    let host = match host.rsplit_once(':') {
+        Some((host, port)) if port.bytes().all(|byte| byte.is_ascii_digit()) => host,
+        _ => host,
+    };
+    Some(host.strip_suffix('.').unwrap_or(host))
+}
+
+/// Matches `host` against `pattern`, label by label, and returns its captures.
+fn match_host<'a>(pattern: &'static str, host: &'a str) -> Option<Vec<(&'static str, &'a str)>> {
+    if pattern.split('.').count() != host.split('.').count() {
+        return None;
+    }
+    let mut captures = Vec::new();
+    for (pattern, label) in pattern.split('.').zip(host.split('.')) {
+        match pattern.strip_prefix(':') {
+            Some(_) if label.is_empty() => return None,
+            Some(name) => captures.push((name, label)),
+            None if pattern.eq_ignore_ascii_case(label) => {}
+            None => return None,
+        }
+    }
+    Some(captures)
+}
+
+/// Extracts the path struct `T` of a route in the block for `host`, from the captures of its
+/// path and of the host of the request.
+pub fn with_host_captures<T>(
+    parts: &Parts,
+    host: &'static str,
+    mut captures: Vec<(String, String)>,
+) -> Result<T, FailedToDeserializeCaptures>
+where
+    T: DeserializeOwned,
+{
+    if let Some(host_captures) =
+        request_host(&parts.headers, &parts.uri).and_then(|name| match_host(host, name))
+    {
+        captures.extend(
+            host_captures
+                .into_iter()
+                .map(|(name, value)| (name.to_owned(), value.to_ascii_lowercase())),
+        );
+    }
+    crate::de::from_captures(&captures)
+}
+
+/// Redirects to the form of `uri` with a trailing slash if `slash`, or without one.
+async fn redirect(OriginalUri(uri): OriginalUri, slash: bool) -> Redirect {
+    let path = uri.path();
+    let mut location = if slash {
+        format!("{path}/")
+    } else {
+        path.trim_end_matches('/').to_owned()
+    };
+    if let Some(query) = uri.query() {
+        location.push('?');
+        location.push_str(query);
+    }
+    Redirect::permanent(&location)
+}
+
+// The characters encoded in a capture, by the `Display` of path structs and by aliases, from
+// https://github.com/servo/rust-url/blob/master/url/src/parser.rs
+const FRAGMENT: &AsciiSet = &CONTROLS.add(b' ').add(b'"').add(b'<').add(b'>').add(b'`');
+const PATH: &AsciiSet = &FRAGMENT.add(b'#').add(b'?').add(b'{').add(b'}');
+pub const PATH_SEGMENT: &AsciiSet = &PATH.add(b'/').add(b'%');
+
+/// Serves an alias of the route at `canonical` by redirecting to it with a
+/// `301 Moved Permanently`, filling its captures with the ones of the alias.
+pub fn redirect_alias<S>(canonical: &'static str) -> MethodRouter<S>
+where
+    S: Clone + Send + Sync + 'static,
+{
+    any(
+        move |OriginalUri(original): OriginalUri,
+              uri: Uri,
+              Path(captures): Path<Vec<(String, String)>>| async move {
+            // The part of the path before the alias, when nested.
+            let prefix = original.path().strip_suffix(uri.path()).unwrap_or_default();
+            let path = canonical
+                .split('/')
+                .map(|segment| {
+                    let capture = segment
+                        .strip_prefix(':')
+                        .or_else(|| segment.strip_prefix('*'));
+                    match capture.and_then(|name| captures.iter().find(|(other, _)| other == name))
+                    {
+                        Some((_, value)) => value
+                            .split('/')
+                            .map(|part| utf8_percent_encode(part, PATH_SEGMENT).to_string())
+                            .collect::<Vec<_>>()
+                            .join("/"),
+                        None => segment.to_owned(),
+                    }
+                })
+                .collect::<Vec<_>>()
+                .join("/");
+            let mut location = format!("{prefix}{path}");
+            if let Some(query) = original.query() {
+                location.push('?');
+                location.push_str(query);
+            }
+            (
+                StatusCode::MOVED_PERMANENTLY,
+                [(header::LOCATION, location)],
+            )
+        },
+    )
+}
+
+/// Serves a `308 Permanent Redirect` to `target` for every method, under the same prefix as
+/// the redirecting route when nested.
+pub fn redirect_to<S, P>(target: P) -> MethodRouter<S>
+where
+    S: Clone + Send + Sync + 'static,
+    P: TypedPath + Display,
+{
+    let target = target.to_string();
+    any(
+        move |OriginalUri(original): OriginalUri, uri: Uri| async move {
+            let prefix = original.path().strip_suffix(uri.path()).unwrap_or_default();
+            Redirect::permanent(&format!("{prefix}{target}"))
+        },
+    )
+}
+
+/// Serves `text` as `text/plain` for every method.
+pub fn text<S>(text: &'static str) -> MethodRouter<S>
+where
+    S: Clone + Send + Sync + 'static,
+{
+    any(move || async move { text })
+}
+
+/// Serves an empty response with `status` for every method.
+pub fn status<S>(status: u16) -> MethodRouter<S>
+where
+    S: Clone + Send + Sync + 'static,
+{
+    let status = StatusCode::from_u16(status).expect("status codes are checked by `routes!`");
+    any(move || async move { status })
+}
+
+/// Method router fallback serving the methods `MethodFilter` can't express, and the route's
+/// `any` handler.
+///
+/// Requests with any other method are passed to the `method_not_allowed` handler, which
+/// defaults to a plain `405 Method Not Allowed`. Either way the response lists `allow` in its
+/// `Allow` header.
+pub struct MethodFallback<S> {
+    allow: &'static str,
+    extensions: Arc<Vec<(Method, MethodRouter<S>)>>,
+    any: Option<MethodRouter<S>>,
+    method_not_allowed: MethodRouter<S>,
+}
+
+impl<S> MethodFallback<S>
+where
+    S: Clone + Send + Sync + 'static,
+{
+    pub fn new(allow: &'static str) -> Self {
+        Self {
+            allow,
+            extensions: Arc::new(Vec::new()),
+            any: None,
+            method_not_allowed: any(|allowed: AllowedMethods| async move {
+                (StatusCode::METHOD_NOT_ALLOWED, allowed, ())
+            }),
+        }
+    }
+
+    pub fn on<H, T>(mut self, method: &'static str, handler: H) -> Self
+    where
+        H: Handler<T, S>,
+        T: 'static,
+    {
+        let method = Method::from_bytes(method.as_bytes()).expect("invalid method name");
+        Arc::make_mut(&mut self.extensions).push((method, any(handler)));
+        self
+    }
+
+    pub fn any<H, T>(mut self, handler: H) -> Self
+    where
+        H: Handler<T, S>,
+        T: 'static,
+    {
+        self.any = Some(any(handler));
+        self
+    }
+
+    pub fn method_not_allowed<H, T>(mut self, handler: H) -> Self
+    where
+        H: Handler<T, S>,
+        T: 'static,
+    {
+        self.method_not_allowed = any(handler);
+        self
+    }
+
+    /// Applies `layer` to every request reaching the fallback, like `MethodRouter::layer`.
+    pub fn layer<L>(mut self, layer: L) -> Self
+    where
+        L: Layer<Route> + Clone + Send + 'static,
+        L::Service: Service<Request> + Clone + Send + 'static,
+        <L::Service as Service<Request>>::Response: IntoResponse + 'static,
+        <L::Service as Service<Request>>::Error: Into<Infallible> + 'static,
+        <L::Service as Service<Request>>::Future: Send + 'static,
+    {
+        self.method_not_allowed = self.method_not_allowed.layer(layer.clone());
+        self.route_layer(layer)
+    }
+
+    /// Applies `layer` to the extension methods and the `any` handler, but not to `405`
+    /// responses, like `MethodRouter::route_layer`.
+    pub fn route_layer<L>(mut self, layer: L) -> Self
+    where
+        L: Layer<Route> + Clone + Send + 'static,
+        L::Service: Service<Request> + Clone + Send + 'static,
+        <L::Service as Service<Request>>::Response: IntoResponse + 'static,
+        <L::Service as Service<Request>>::Error: Into<Infallible> + 'static,
+        <L::Service as Service<Request>>::Future: Send + 'static,
+    {
+        self.extensions = Arc::new(
+            self.extensions
+                .iter()
+                .map(|(method, endpoint)| (method.clone(), endpoint.clone().layer(layer.clone())))
+                .collect(),
+        );
+        self.any = self.any.map(|any| any.layer(layer));
+        self
+    }
+}
+
+impl<S> Clone for MethodFallback<S> {
+    fn clone(&self) -> Self {
+        Self {
+            allow: self.allow,
+            extensions: Arc::clone(&self.extensions),
+            any: self.any.clone(),
+            method_not_allowed: self.method_not_allowed.clone(),
+        }
+    }
+}
+
+impl<S> Handler<(), S> for MethodFallback<S>
+where
+    S: Clone + Send + Sync + 'static,
+{
+    type Future = Pin<Box<dyn Future<Output = Response> + Send>>;
+
+    fn call(self, mut req: Request, state: S) -> Self::Future {
+        Box::pin(async move {
+            let endpoint = self
+                .extensions
+                .iter()
+                .find(|(method, _)| method == req.method())
+                .map(|(_, endpoint)| endpoint)
+                .or(self.any.as_ref());
+            if let Some(endpoint) = endpoint {
+                return Handler::call(endpoint.clone(), req, state).await;
+            }
+            let allowed = AllowedMethods::new(self.allow);
+            req.extensions_mut().insert(allowed);
+            let res = Handler::call(self.method_not_allowed, req, state).await;
+            if res.headers().contains_key(header::ALLOW) {
+                res
+            } else {
+                (allowed, res).into_response()
+            }
+        })
+    }
+}
+
+/// The handlers of a method for different media types, choosing one from the `Accept` header
+/// of the request, or from its `Content-Type`. Requests accepting none
+/// of them are passed to the `not_acceptable` handler, which defaults to a plain
+/// `406 Not Acceptable`, and responses vary on `Accept`. Requests with a body of none of them
+/// get a plain `415 Unsupported Media Type`.
+pub struct Negotiate<S> {
+    by_content_type: bool,
+    handlers: Arc<Vec<(&'static str, MethodRouter<S>)>>,
+    not_acceptable: MethodRouter<S>,
+}
+
+impl<S> Negotiate<S>
+where
+    S: Clone + Send + Sync + 'static,
+{
+    pub fn accept() -> Self {
+        Self {
+            by_content_type: false,
+            handlers: Arc::new(Vec::new()),
+            not_acceptable: any(|| async { StatusCode::NOT_ACCEPTABLE }),
+        }
+    }
+
+    pub fn content_type() -> Self {
+        Self {
+            by_content_type: true,
+            handlers: Arc::new(Vec::new()),
+            not_acceptable: any(|| async { StatusCode::UNSUPPORTED_MEDIA_TYPE }),
+        }
+    }
+
+    pub fn on<H, T>(mut self, media: &'static str, handler: H) -> Self
+    where
+        H: Handler<T, S>,
+        T: 'static,
+    {
+        Arc::make_mut(&mut self.handlers).push((media, any(handler)));
+        self
+    }
+
+    pub fn not_acceptable<H, T>(mut self, handler: H) -> Self
+    where
+        H: Handler<T, S>,
+        T: 'static,
+    {
+        self.not_acceptable = any(handler);
+        self
+    }
+
+    /// The handler for the `Content-Type` of `req`, ignoring its parameters.
+    fn by_content_type(&self, req: &Request) -> Option<&MethodRouter<S>> {
+        let content_type = req.headers().get(header::CONTENT_TYPE)?.to_str().ok()?;
+        let essence = content_type.split(';').next()?.trim();
+        self.handlers
+            .iter()
+            .find(|(media, _)| media.eq_ignore_ascii_case(essence))
+            .map(|(_, handler)| handler)
+    }
+
+    /// The first of the handlers for the media types `req` prefers, or the first handler when
+    /// it has no `Accept` header.
+    fn by_accept(&self, req: &Request) -> Option<&MethodRouter<S>> {
+        let accept = req
+            .headers()
+            .get_all(header::ACCEPT)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .collect::<Vec<_>>()
+            .join(",");
+        if accept.trim().is_empty() {
+            return self.handlers.first().map(|(_, handler)| handler);
+        }
+        self.handlers
+            .iter()
+            .map(|(media, handler)| (quality(&accept, media), handler))
+            .filter(|(quality, _)| *quality > 0.0)
+            .fold(
+                None,
+                |best: Option<(f32, _)>, (quality, handler)| match best {
+                    Some((best_quality, _)) if best_quality >= quality => best,
+                    _ => Some((quality, handler)),
+                },
+            )
+            .map(|(_, handler)| handler)
+    }
+}
+
+impl<S> Clone for Negotiate<S> {
+    fn clone(&self) -> Self {
+        Self {
+            by_content_type: self.by_content_type,
+            handlers: Arc::clone(&self.handlers),
+            not_acceptable: self.not_acceptable.clone(),
+        }
+    }
+}
+
+impl<S> Handler<(), S> for Negotiate<S>
+where
+    S: Clone + Send + Sync + 'static,
+{
+    type Future = Pin<Box<dyn Future<Output = Response> + Send>>;
+
+    fn call(self, req: Request, state: S) -> Self::Future {
+        Box::pin(async move {
+            let endpoint = if self.by_content_type {
+                self.by_content_type(&req)
+            } else {
+                self.by_accept(&req)
+            };
+            let endpoint = endpoint.unwrap_or(&self.not_acceptable).clone();
+            let mut res = Handler::call(endpoint, req, state).await;
+            if !self.by_content_type {
+                res.headers_mut()
+                    .append(header::VARY, HeaderValue::from_static("accept"));
+            }
+            res
+        })
+    }
+}
+
+/// A route served only to requests with the header `name`, passing other requests to the
+/// `rejection` handler, which defaults to a plain `400 Bad Request`. Either way the response
+/// varies on the header.
+pub struct RequireHeader<S> {
+    name: HeaderName,
+    endpoint: MethodRouter<S>,
+    rejection: MethodRouter<S>,
+}
+
+impl<S> RequireHeader<S>
+where
+    S: Clone + Send + Sync + 'static,
+{
+    pub fn new(name: &'static str, endpoint: MethodRouter<S>) -> Self {
+        Self {
+            name: HeaderName::from_static(name),
+            endpoint,
+            rejection: any(|| async { StatusCode::BAD_REQUEST }),
+        }
+    }
+
+    pub fn rejection<H, T>(mut self, handler: H) -> Self
+    where
+        H: Handler<T, S>,
+        T: 'static,
+    {
+        self.rejection = any(handler);
+        self
+    }
+}
+
+impl<S> Clone for RequireHeader<S> {
+    fn clone(&self) -> Self {
+        Self {
+            name: self.name.clone(),
+            endpoint: self.endpoint.clone(),
+            rejection: self.rejection.clone(),
+        }
+    }
+}
+
+impl<S> Handler<(), S> for RequireHeader<S>
+where
+    S: Clone + Send + Sync + 'static,
+{
+    type Future = Pin<Box<dyn Future<Output = Response> + Send>>;
+
+    fn call(self, req: Request, state: S) -> Self::Future {
+        Box::pin(async move {
+            let endpoint = if req.headers().contains_key(&self.name) {
+                self.endpoint
+            } else {
+                self.rejection
+            };
+            let mut res = Handler::call(endpoint, req, state).await;
+            res.headers_mut()
+                .append(header::VARY, HeaderValue::from(self.name));
+            res
+        })
+    }
+}
+
+/// Marks [`Predicate`]s that are plain functions returning `bool`.
+pub struct SyncPredicate;
+
+/// Marks [`Predicate`]s that are `async` functions resolving to `bool`.
+pub struct AsyncPredicate;
+
+/// A `guard` function over the request parts and the state `S` of the block declaring it,
+/// sync or async. `M` tells the two apart, like the extractors of axum handlers.
+pub trait Predicate<M, S>: Clone + Send + Sync + 'static {
+    fn check<'a>(
+        &'a self,
+        parts: &'a Parts,
+        state: &'a S,
+    ) -> Pin<Box<dyn Future<Output = bool> + Send + 'a>>;
+}
+
+impl<F, S> Predicate<SyncPredicate, S> for F
+where
+    F: Fn(&Parts, &S) -> bool + Clone + Send + Sync + 'static,
+{
+    fn check<'a>(
+        &'a self,
+        parts: &'a Parts,
+        state: &'a S,
+    ) -> Pin<Box<dyn Future<Output = bool> + Send + 'a>> {
+        Box::pin(std::future::ready(self(parts, state)))
+    }
+}
+
+/// An `async` function over borrowed parts and state, whose future borrows them too.
+pub trait AsyncPredicateFn<'a, S: 'a> {
+    type Future: Future<Output = bool> + Send + 'a;
+
+    fn call(&self, parts: &'a Parts, state: &'a S) -> Self::Future;
+}
+
+impl<'a, S, F, Fut> AsyncPredicateFn<'a, S> for F
+where
+    S: 'a,
+    F: Fn(&'a Parts, &'a S) -> Fut,
+    Fut: Future<Output = bool> + Send + 'a,
+{
+    type Future = Fut;
+
+    fn call(&self, parts: &'a Parts, state: &'a S) -> Fut {
+        self(parts, state)
+    }
+}
+
+impl<F, S> Predicate<AsyncPredicate, S> for F
+where
+    F: for<'a> AsyncPredicateFn<'a, S> + Clone + Send + Sync + 'static,
+{
+    fn check<'a>(
+        &'a self,
+        parts: &'a Parts,
+        state: &'a S,
+    ) -> Pin<Box<dyn Future<Output = bool> + Send + 'a>> {
+        Box::pin(self.call(parts, state))
+    }
+}
+
+type GuardFn<S> = dyn for<'a> Fn(&'a Parts, &'a S) -> Pin<Box<dyn Future<Output = bool> + Send + 'a>>
+    + Send
+    + Sync;
+
+/// A `guard` ready to check requests to a router with state `S`.
+pub struct Guard<S>(Arc<GuardFn<S>>);
+
+impl<S> Clone for Guard<S> {
+    fn clone(&self) -> Self {
+        Self(Arc::clone(&self.0))
+    }
+}
+
+/// Wraps the `guard` of a block with state `Sub` so it checks requests to a router with state
+/// `S`, extracting `Sub` from `S` with [`FromRef`] on every request.
+pub fn guard<G, M, Sub, S>(guard: G) -> Guard<S>
+where
+    G: Predicate<M, Sub>,
+    Sub: FromRef<S> + Send + Sync + 'static,
+{
+    Guard(Arc::new(move |parts, state| {
+        let sub = Sub::from_ref(state);
+        let guard = guard.clone();
+        Box::pin(async move { guard.check(parts, &sub).await })
+    }))
+}
+
+type GuardedHandlers<S> = Vec<(Option<Guard<S>>, MethodRouter<S>)>;
+
+/// Handlers tried in order, each behind the guard it was added with, if any, falling through
+/// to the next one when its guard fails, and to the `fallback` of the `routes!` after the last
+/// one, or of the `routes!` it's merged or nested in.
+pub struct Guarded<S> {
+    handlers: Arc<GuardedHandlers<S>>,
+    fallback: MethodRouter<S>,
+}
+
+impl<S> Guarded<S>
+where
+    S: Clone + Send + Sync + 'static,
+{
+    pub fn new(fallback: MethodRouter<S>) -> Self {
+        Self {
+            handlers: Arc::new(Vec::new()),
+            fallback,
+        }
+    }
+
+    pub fn on<H, T>(mut self, guard: Option<Guard<S>>, handler: H) -> Self
+    where
+        H: Handler<T, S>,
+        T: 'static,
+    {
+        Arc::make_mut(&mut self.handlers).push((guard, any(handler)));
+        self
+    }
+}
+
+impl<S> Clone for Guarded<S> {
+    fn clone(&self) -> Self {
+        Self {
+            handlers: Arc::clone(&self.handlers),
+            fallback: self.fallback.clone(),
+        }
+    }
+}
+
+impl<S> Handler<(), S> for Guarded<S>
+where
+    S: Clone + Send + Sync + 'static,
+{
+    type Future = Pin<Box<dyn Future<Output = Response> + Send>>;
+
+    fn call(self, req: Request, state: S) -> Self::Future {
+        Box::pin(async move {
+            let (parts, body) = req.into_parts();
+            let mut endpoint = None;
+            for (guard, handler) in self.handlers.iter() {
+                if let Some(guard) = guard {
+                    if !(guard.0)(&parts, &state).await {
+                        continue;
+                    }
+                }
+                endpoint = Some(handler.clone());
+                break;
+            }
+            let endpoint = endpoint.unwrap_or(self.fallback);
+            Handler::call(endpoint, Request::from_parts(parts, body), state).await
+        })
+    }
+}
+
+/// The fallback of a `routes!` merging or nesting others, as a request extension for the
+/// guards and hosts of the ones without a fallback, since axum has already routed their
+/// requests past it. The innermost one wins, its layer being the last to run.
+#[derive(Clone)]
+pub struct OuterFallback<S>(pub MethodRouter<S>);
+
+/// The fallback of guards and hosts in a `routes!` without one, the [`OuterFallback`] of the
+/// request, if any, or a plain `404 Not Found`.
+pub fn not_found<S>() -> MethodRouter<S>
+where
+    S: Clone + Send + Sync + 'static,
+{
+    any(|State(state): State<S>, req: Request| async move {
+        match req.extensions().get::<OuterFallback<S>>().cloned() {
+            Some(OuterFallback(fallback)) => Handler::call(fallback, req, state).await,
+            None => StatusCode::NOT_FOUND.into_response(),
+        }
+    })
+}
+
+/// The quality `accept` gives to `media`, from its most specific range matching it.
+fn quality(accept: &str, media: &str) -> f32 {
+    let (ty, _) = media.split_once('/').unwrap_or((media, ""));
+    accept
+        .split(',')
+        .filter_map(|range| {
+            let mut params = range.split(';');
+            let range = params.next()?.trim();
+            let specificity = if range.eq_ignore_ascii_case(media) {
+                2
+            } else if range
+                .strip_suffix("/*")
+                .is_some_and(|range| range.eq_ignore_ascii_case(ty))
+            {
+                1
+            } else if range == "*/*" {
+                0
+            } else {
+                return None;
+            };
+            let quality = params
+                .filter_map(|param| param.trim().strip_prefix("q="))
+                .find_map(|quality| quality.trim().parse::<f32>().ok())
+                .unwrap_or(1.0);
+            Some((specificity, quality))
+        })
+        .max_by_key(|(specificity, _)| *specificity)
+        .map_or(0.0, |(_, quality)| quality)
+}
+
+/// Implemented by `routes!` for its struct, so handlers registered with
+/// [`handler`](crate::handler) know the state `S` of the table they join.
+pub trait RouteTable<S>: 'static {}
+
+/// A handler registered with [`handler`](crate::handler), collected by `inventory`.
+pub struct Registration {
+    /// `TypeId::of` the route table.
+    pub table: fn() -> TypeId,
+    pub path: &'static str,
+    pub method: &'static str,
+    /// Adds the handler to a `MethodRouter` with the state of the table.
+    pub register: fn(&mut dyn Any),
+    /// Where the handler is defined, for duplicate errors.
+    pub location: &'static str,
+}
+
+inventory::collect!(Registration);
+
+/// Adds `handler` for `filter` to `method_router`, a `MethodRouter<S>`.
+pub fn register<R, S, H, T, P>(method_router: &mut dyn Any, filter: MethodFilter, handler: H)
+where
+    R: RouteTable<S>,
+    S: Clone + Send + Sync + 'static,
+    H: Handler<T, S>,
+    T: SecondElementIs<P> + 'static,
+    P: TypedPath,
+{
+    let method_router = method_router
+        .downcast_mut::<MethodRouter<S>>()
+        .expect("method router of another state");
+    *method_router = std::mem::take(method_router).on(filter, handler);
+}
+
+/// Adds every handler registered for the table `R` to `router`.
+///
+/// # Panics
+///
+/// Panics if two handlers are registered for the same method and path.
+pub fn registered<R, S>(mut router: Router<S>) -> Router<S>
+where
+    R: RouteTable<S>,
+    S: Clone + Send + Sync + 'static,
+{
+    let mut routes: Vec<(&'static str, MethodRouter<S>)> = Vec::new();
+    let mut seen: Vec<&Registration> = Vec::new();
+    for registration in inventory::iter::<Registration> {
+        if (registration.table)() != TypeId::of::<R>() {
+            continue;
+        }
+        if let Some(other) = seen
+            .iter()
+            .find(|other| other.path == registration.path && other.method == registration.method)
+        {
+            panic!(
+                "duplicate {} handler for `{}`, registered at {} and {}",
+                registration.method.to_lowercase(),
+                registration.path,
+                other.location,
+                registration.location,
+            );
+        }
+        seen.push(registration);
+        let index = match routes
+            .iter()
+            .position(|(path, _)| *path == registration.path)
+        {
+            Some(index) => index,
+            None => {
+                routes.push((registration.path, MethodRouter::new()));
+                routes.len() - 1
+            }
+        };
+        (registration.register)(&mut routes[index].1);
+    }
+    for (path, method_router) in routes {
+        router = router.route(path, method_router);
+    }
+    router
+}
@@ -0,0 +1,101 @@
+use std::fmt;
+
+use axum::async_trait;
+use axum::extract::FromRequestParts;
+use axum::http::request::Parts;
+use axum::http::{header, HeaderValue, Method, StatusCode};
+use axum::response::{IntoResponseParts, ResponseParts};
+
+/// The methods a route serves, available to its `method_not_allowed` handler.
+///
+/// Used as a response part it sets the `Allow` header, so a handler can return
+/// `(StatusCode::METHOD_NOT_ALLOWED, allowed, body)`.
+///
+/// # Example
+/// ```rust
+/// use axum::http::StatusCode;
+/// use axum::response::IntoResponse;
+/// use axum::Router;
+/// use better_routes::{routes, AllowedMethods};
+/// use serde::Deserialize;
+///
+/// #[derive(Deserialize)]
+/// struct User {
+///     id: usize,
+/// }
+///
+/// async fn get_user(_: User) {}
+///
+/// async fn user_method_not_allowed(user: User, allowed: AllowedMethods) -> impl IntoResponse {
+///     let message = format!("user {} only supports {allowed}", user.id);
+///     (StatusCode::METHOD_NOT_ALLOWED, allowed, message)
+/// }
+///
+/// routes! {
+///     name => AllRoutes,
+///     "/user/:id" => User {
+///         get => get_user,
+///         method_not_allowed => user_method_not_allowed,
+///     }
+/// }
+///
+/// # fn main() {
+/// let r: Router = AllRoutes::routes();
+/// # }
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AllowedMethods {
+    allow: &'static str,
+}
+
+impl AllowedMethods {
+    pub(crate) fn new(allow: &'static str) -> Self {
+        Self { allow }
+    }
+
+    /// Iterates over the allowed method names, such as `"GET"`.
+    pub fn iter(&self) -> impl Iterator<Item = &'static str> {
+        self.allow.split(',').filter(|method| !method.is_empty())
+    }
+
+    /// Returns `true` if `method` is one of the allowed methods.
+    pub fn contains(&self, method: &Method) -> bool {
+        self.iter().any(|allowed| allowed == method.as_str())
+    }
+
+    /// The value of the `Allow` header, such as `"GET,HEAD,POST"`.
+    pub fn as_str(&self) -> &'static str {
+        self.allow
+    }
+}
+
+impl fmt::Display for AllowedMethods {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.allow.replace(',', ", "))
+    }
+}
+
+#[async_trait]
+impl<S> FromRequestParts<S> for AllowedMethods
+where
+    S: Send + Sync,
+{
+    type Rejection = (StatusCode, &'static str);
+
+    async fn from_request_parts(parts: &mut Parts, _: &S) -> Result<Self, Self::Rejection> {
+        parts.extensions.get::<Self>().copied().ok_or((
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "`AllowedMethods` can only be extracted by `method_not_allowed` handlers",
+        ))
+    }
+}
+
+impl IntoResponseParts for AllowedMethods {
+    type Error = std::convert::Infallible;
+
+    fn into_response_parts(self, mut res: ResponseParts) -> Result<ResponseParts, Self::Error> {
+        res.headers_mut()
+            .insert(header::ALLOW, HeaderValue::from_static(self.allow));
+        Ok(res)
+    }
+}
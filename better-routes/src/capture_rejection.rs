@@ -0,0 +1,202 @@
+use std::error::Error;
+use std::fmt;
+
+use axum::extract::rejection::PathRejection;
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+
+/// A path capture that doesn't satisfy its constraint, such as `:id<\d+>` or `:page<1..=1000>`.
+///
+/// Responds with `400 Bad Request`. Routes with their own rejection convert it with `From`,
+/// like the `PathRejection` of their path struct.
+///
+/// # Example
+/// ```rust
+/// use axum::extract::rejection::PathRejection;
+/// use axum::http::StatusCode;
+/// use axum::response::{IntoResponse, Response};
+/// use axum::Router;
+/// use better_routes::{routes, InvalidCapture};
+/// use serde::Deserialize;
+///
+/// struct PageRejection(String);
+///
+/// impl From<PathRejection> for PageRejection {
+///     fn from(rejection: PathRejection) -> Self {
+///         Self(rejection.body_text())
+///     }
+/// }
+///
+/// impl From<InvalidCapture> for PageRejection {
+///     fn from(invalid: InvalidCapture) -> Self {
+///         Self(format!("no page {}", invalid.value()))
+///     }
+/// }
+///
+/// impl IntoResponse for PageRejection {
+///     fn into_response(self) -> Response {
+///         (StatusCode::NOT_FOUND, self.0).into_response()
+///     }
+/// }
+///
+/// #[derive(Deserialize)]
+/// struct Page {
+///     page: u32,
+/// }
+///
+/// async fn get_page(_: Page) {}
+///
+/// routes! {
+///     name => AllRoutes,
+///     "/page/:page<1..=1000>" => rejection PageRejection => Page {
+///         get => get_page
+///     },
+/// }
+///
+/// # fn main() {
+/// let r: Router = AllRoutes::routes();
+/// # }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InvalidCapture {
+    name: &'static str,
+    constraint: &'static str,
+    value: String,
+}
+
+impl InvalidCapture {
+    pub(crate) fn new(name: &'static str, constraint: &'static str, value: String) -> Self {
+        Self {
+            name,
+            constraint,
+            value,
+        }
+    }
+
+    /// The name of the capture, such as `"id"`.
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    /// The constraint, as written between `<` and `>`, such as `"\d+"`.
+    pub fn constraint(&self) -> &'static str {
+        self.constraint
+    }
+
+    /// The percent-decoded value of the capture.
+    pub fn value(&self) -> &str {
+        &self.value
+    }
+}
+
+impl fmt::Display for InvalidCapture {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "`{}` doesn't match `{}` for `{}`",
+            self.value, self.constraint, self.name
+        )
+    }
+}
+
+impl Error for InvalidCapture {}
+
+impl IntoResponse for InvalidCapture {
+    fn into_response(self) -> Response {
+        (StatusCode::BAD_REQUEST, format!("Invalid URL: {self}")).into_response()
+    }
+}
+
+/// The path and host captures of a route in a `host` block couldn't be deserialized into its
+/// path struct.
+///
+/// Responds with `400 Bad Request`, like the `PathRejection` of path structs without host
+/// captures.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FailedToDeserializeCaptures {
+    message: String,
+}
+
+impl FailedToDeserializeCaptures {
+    pub(crate) fn new(message: String) -> Self {
+        Self { message }
+    }
+
+    /// The error of the deserializer.
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+}
+
+impl fmt::Display for FailedToDeserializeCaptures {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Failed to deserialize captures: {}", self.message)
+    }
+}
+
+impl Error for FailedToDeserializeCaptures {}
+
+impl IntoResponse for FailedToDeserializeCaptures {
+    fn into_response(self) -> Response {
+        (StatusCode::BAD_REQUEST, self.to_string()).into_response()
+    }
+}
+
+/// Rejection of path structs with constrained or host captures and no rejection of their own.
+#[derive(Debug)]
+pub enum CaptureRejection {
+    /// The captures couldn't be extracted or deserialized.
+    Path(PathRejection),
+    /// A capture doesn't satisfy its constraint.
+    Invalid(InvalidCapture),
+    /// The captures of a route with host captures couldn't be deserialized.
+    Deserialize(FailedToDeserializeCaptures),
+}
+
+impl From<PathRejection> for CaptureRejection {
+    fn from(rejection: PathRejection) -> Self {
+        Self::Path(rejection)
+    }
+}
+
+impl From<InvalidCapture> for CaptureRejection {
+    fn from(invalid: InvalidCapture) -> Self {
+        Self::Invalid(invalid)
+    }
+}
+
+impl From<FailedToDeserializeCaptures> for CaptureRejection {
+    fn from(failed: FailedToDeserializeCaptures) -> Self {
+        Self::Deserialize(failed)
+    }
+}
+
+impl fmt::Display for CaptureRejection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Path(rejection) => rejection.fmt(f),
+            Self::Invalid(invalid) => invalid.fmt(f),
+            Self::Deserialize(failed) => failed.fmt(f),
+        }
+    }
+}
+
+impl Error for CaptureRejection {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Path(rejection) => Some(rejection),
+            Self::Invalid(invalid) => Some(invalid),
+            Self::Deserialize(failed) => Some(failed),
+        }
+    }
+}
+
+impl IntoResponse for CaptureRejection {
+    fn into_response(self) -> Response {
+        match self {
+            Self::Path(rejection) => rejection.into_response(),
+            Self::Invalid(invalid) => invalid.into_response(),
+            Self::Deserialize(failed) => failed.into_response(),
+        }
+    }
+}
@@ -15,7 +15,6 @@
 //! use axum::{
 //!     extract::rejection::PathRejection, http::StatusCode, response::IntoResponse, Json, Router,
 //! };
-//! use axum_extra::routing::RouterExt;
 //! use better_routes::routes;
 //! use serde::Deserialize;
 //!
@@ -168,7 +167,6 @@
 /// # Example
 /// ```rust
 /// use axum::Router;
-/// use axum_extra::routing::RouterExt;
 /// use better_routes::routes;
 /// use serde::Deserialize;
 ///
@@ -193,10 +191,807 @@
 /// }
 /// ```
 ///
+/// # With Multiple and Extension Methods
+///
+/// Besides `get`, `post`, `put`, `patch` and `delete`, a route accepts `head`, `options`,
+/// `trace` and `connect`. Several methods can share one handler by listing them in
+/// brackets, and methods outside that set, such as WebDAV's `PROPFIND`, are written as
+/// `method "NAME"`.
+/// ```rust
+/// use axum::Router;
+/// use better_routes::routes;
+/// use serde::Deserialize;
+///
+/// #[derive(Deserialize)]
+/// struct Page {
+///     id: usize,
+/// }
+///
+/// async fn show(_: Page) {}
+///
+/// async fn purge(_: Page) {}
+///
+/// async fn properties(_: Page) {}
+///
+/// routes! {
+///     name => AllRoutes,
+///     "/page/:id" => Page {
+///         [get, head] => show,
+///         method "PURGE" => purge,
+///         [options, method "PROPFIND"] => properties,
+///     }
+/// }
+///
+/// #[tokio::main]
+/// async fn main() {
+///     let r: Router = AllRoutes::routes();
+/// }
+/// ```
+///
+/// # With a Catch-All Method Handler
+///
+/// `any` handles every method that no other handler of the route claims.
+/// ```rust
+/// use axum::extract::Request;
+/// use axum::Router;
+/// use better_routes::routes;
+/// use serde::Deserialize;
+///
+/// #[derive(Deserialize)]
+/// struct Graphql;
+///
+/// async fn playground(_: Graphql) {}
+///
+/// async fn graphql(_: Graphql, _: Request) {}
+///
+/// routes! {
+///     name => AllRoutes,
+///     "/graphql" => Graphql {
+///         get => playground,
+///         any => graphql,
+///     }
+/// }
+///
+/// #[tokio::main]
+/// async fn main() {
+///     let r: Router = AllRoutes::routes();
+/// }
+/// ```
+///
+/// # With Method Not Allowed Handlers
+///
+/// `method_not_allowed` replaces the plain `405 Method Not Allowed` response, either for every
+/// route at the top level or for a single route inside its block. The handler can extract
+/// [`AllowedMethods`]; a route's own handler can also take its path struct. The `Allow` header
+/// is added to the response unless the handler sets it.
+/// ```rust
+/// use axum::http::StatusCode;
+/// use axum::Router;
+/// use better_routes::{routes, AllowedMethods};
+/// use serde::Deserialize;
+///
+/// #[derive(Deserialize)]
+/// struct Home;
+///
+/// async fn index(_: Home) {}
+///
+/// async fn method_not_allowed(allowed: AllowedMethods) -> (StatusCode, String) {
+///     (StatusCode::METHOD_NOT_ALLOWED, format!("try one of {allowed}"))
+/// }
+///
+/// routes! {
+///     name => AllRoutes,
+///     method_not_allowed => method_not_allowed,
+///     "/" => Home {
+///         get => index
+///     }
+/// }
+///
+/// #[tokio::main]
+/// async fn main() {
+///     let r: Router = AllRoutes::routes();
+/// }
+/// ```
+///
+/// # With Content Negotiation
+///
+/// A method can have a handler for each media type it responds with, `get(html)` or
+/// `get(json)`, or any other as `get("application/ld+json")`, and the one the `Accept` header
+/// of the request prefers is called. `get(html)` is short for `get(accept => html)`. Without an `Accept` header, the first
+/// one is. Requests accepting none of them get a `406 Not Acceptable`, which `not_acceptable`
+/// replaces, either for every route at the top level or for a single route inside its block.
+/// Responses vary on `Accept`. This works for every method, such as a `post` answering with an
+/// HTML fragment or with JSON.
+///
+/// A method can also have a handler for each media type of the body it takes, as in
+/// `post(content_type => form)` or `post(content_type => json)`, with `form` and `multipart`
+/// as shorthands for `application/x-www-form-urlencoded` and `multipart/form-data`. Bodies of
+/// none of them get a `415 Unsupported Media Type`. On `post`, `put` and `patch`, which take a
+/// body, a bare media type is short for `content_type`, so `post(form)` is
+/// `post(content_type => form)`, and responding with a media type needs `accept =>` spelled
+/// out. Handlers with both are chosen by `Content-Type` first, then by `Accept` among the ones
+/// for the same content type.
+/// ```rust
+/// use axum::Router;
+/// use better_routes::routes;
+/// use serde::Deserialize;
+///
+/// #[derive(Deserialize)]
+/// struct Todos;
+///
+/// async fn todos_page(_: Todos) {}
+///
+/// async fn todos_json(_: Todos) {}
+///
+/// async fn create_from_form(_: Todos) {}
+///
+/// async fn create_from_json(_: Todos) {}
+///
+/// async fn create_from_json_for_page(_: Todos) {}
+///
+/// routes! {
+///     name => AllRoutes,
+///     "/todos" => Todos {
+///         get(html) => todos_page,
+///         get(json) => todos_json,
+///         post(form) => create_from_form,
+///         post(content_type => json, accept => json) => create_from_json,
+///         post(content_type => json, accept => html) => create_from_json_for_page,
+///     }
+/// }
+///
+/// #[tokio::main]
+/// async fn main() {
+///     let r: Router = AllRoutes::routes();
+/// }
+/// ```
+///
+/// # With Route Groups
+///
+/// A `group` prepends its path to every route inside it, including the `PATH` and
+/// `Display` impls of their path structs. Groups can be nested, and `"/"` inside a group
+/// refers to the group's path itself.
+/// ```rust
+/// use axum::Router;
+/// use axum_extra::routing::TypedPath;
+/// use better_routes::routes;
+/// use serde::Deserialize;
+///
+/// #[derive(Deserialize)]
+/// struct Todos;
+///
+/// #[derive(Deserialize)]
+/// struct Todo {
+///     id: usize,
+/// }
+///
+/// async fn list_todos(_: Todos) {}
+///
+/// async fn get_todo(_: Todo) {}
+///
+/// routes! {
+///     name => AllRoutes,
+///     "/api" => group {
+///         "/todo" => Todos {
+///             get => list_todos
+///         },
+///         "/todo/:id" => Todo {
+///             get => get_todo
+///         },
+///     }
+/// }
+///
+/// #[tokio::main]
+/// async fn main() {
+///     assert_eq!(Todo { id: 1 }.to_uri(), "/api/todo/1");
+///     let r: Router = AllRoutes::routes();
+/// }
+/// ```
+///
+/// # With Inline Path Structs
+///
+/// A path struct can be defined in place, with its fields or tuple fields between its name and
+/// its handlers. It derives `Deserialize`, `Debug`, `Clone`, `PartialEq` and `Hash`, so `serde`
+/// must be a dependency. Its fields must match the captures of the path, except for named
+/// fields with attributes, like `#[serde(skip)]`.
+/// ```rust
+/// use axum::Router;
+/// use axum_extra::routing::TypedPath;
+/// use better_routes::routes;
+///
+/// async fn get_user(user: User) {}
+///
+/// async fn get_post(Post(user, id): Post) {}
+///
+/// routes! {
+///     name => AllRoutes,
+///     "/user/:id" => pub User { pub id: u64 } {
+///         get => get_user
+///     },
+///     "/user/:user/post/:id" => Post(u64, u64) {
+///         get => get_post
+///     },
+/// }
+///
+/// #[tokio::main]
+/// async fn main() {
+///     assert_eq!(User { id: 1 }.to_uri(), "/user/1");
+///     let r: Router = AllRoutes::routes();
+/// }
+/// ```
+///
+/// # With Typed Captures
+///
+/// Captures can be written `{id: u64}`, or `{*rest: String}` for wildcards, to give their type
+/// in the path. It is checked against the field of the path struct, and `struct Name` defines
+/// an inline path struct with one field per typed capture. `{id}` is the same as `:id`.
+/// ```rust
+/// use axum::Router;
+/// use axum_extra::routing::TypedPath;
+/// use better_routes::routes;
+///
+/// async fn get_post(post: Post) {}
+///
+/// routes! {
+///     name => AllRoutes,
+///     "/user/{user: u64}/post/{slug: String}" => pub struct Post {
+///         get => get_post
+///     },
+/// }
+///
+/// #[tokio::main]
+/// async fn main() {
+///     assert_eq!(Post::PATH, "/user/:user/post/:slug");
+///     let post = Post {
+///         user: 1,
+///         slug: "hello".to_owned(),
+///     };
+///     assert_eq!(post.to_uri(), "/user/1/post/hello");
+///     let r: Router = AllRoutes::routes();
+/// }
+/// ```
+///
+/// # With Capture Constraints
+///
+/// A capture can be followed by a constraint between `<` and `>`: a regex the whole capture
+/// must match, like `:id<\d+>`, or a range of integers, like `:page<1..=1000>`. Constraints are
+/// checked before the path struct is deserialized. A capture that doesn't satisfy its
+/// constraint is rejected with [`InvalidCapture`], converted with `From` into the rejection
+/// of the route, or into a [`CaptureRejection`] without one.
+/// ```rust
+/// use axum::Router;
+/// use better_routes::routes;
+/// use serde::Deserialize;
+///
+/// #[derive(Deserialize)]
+/// struct Post {
+///     slug: String,
+///     page: u32,
+/// }
+///
+/// async fn get_post(_: Post) {}
+///
+/// routes! {
+///     name => AllRoutes,
+///     "/post/:slug<[a-z0-9-]+>/:page<1..=1000>" => Post {
+///         get => get_post
+///     },
+/// }
+///
+/// #[tokio::main]
+/// async fn main() {
+///     let r: Router = AllRoutes::routes();
+/// }
+/// ```
+///
+/// # With Tuple Path Structs
+///
+/// Tuple structs are declared with `(..)` after their name, and get the captures of the path
+/// in order. `#[derive(Path)]` detects them on its own.
+/// ```rust
+/// use axum::Router;
+/// use axum_extra::routing::TypedPath;
+/// use better_routes::routes;
+/// use serde::Deserialize;
+///
+/// #[derive(Deserialize)]
+/// struct Pair(String, u32);
+///
+/// async fn get_pair(Pair(name, count): Pair) {}
+///
+/// routes! {
+///     name => AllRoutes,
+///     "/pair/:name/:count" => Pair(..) {
+///         get => get_pair
+///     },
+/// }
+///
+/// #[tokio::main]
+/// async fn main() {
+///     assert_eq!(Pair("a".to_owned(), 2).to_uri(), "/pair/a/2");
+///     let r: Router = AllRoutes::routes();
+/// }
+/// ```
+///
+/// # With Generic Path Structs
+///
+/// Generic path structs declare their parameters with their bounds, as on an impl, and the
+/// generated impls cover every instantiation. Handlers pick one, and the arguments of the path
+/// struct are inferred from them.
+/// ```rust
+/// use std::marker::PhantomData;
+///
+/// use axum::Router;
+/// use axum_extra::routing::TypedPath;
+/// use better_routes::routes;
+/// use serde::Deserialize;
+///
+/// trait Kind: Send + 'static {}
+///
+/// struct User;
+///
+/// impl Kind for User {}
+///
+/// #[derive(Deserialize)]
+/// struct Item<K> {
+///     id: usize,
+///     #[serde(skip)]
+///     kind: PhantomData<K>,
+/// }
+///
+/// async fn get_user(_: Item<User>) {}
+///
+/// routes! {
+///     name => AllRoutes,
+///     "/user/:id" => Item<K: Kind> {
+///         get => get_user
+///     },
+/// }
+///
+/// #[tokio::main]
+/// async fn main() {
+///     let item = Item::<User> {
+///         id: 1,
+///         kind: PhantomData,
+///     };
+///     assert_eq!(item.to_uri(), "/user/1");
+///     let r: Router = AllRoutes::routes();
+/// }
+/// ```
+///
+/// # With Merged and Nested Routes
+///
+/// `merge => OtherRoutes` at the top of `routes!` adds the routes of another [`routes!`]
+/// struct, and `"/prefix" => nest OtherRoutes` adds them under a prefix, including the one of
+/// its group. Both must use the same `state`.
+/// Unlike with a `group`, the `PATH` and `Display` impls of nested path structs don't
+/// include the prefix, and its captures can't be typed or constrained.
+/// ```rust
+/// use axum::Router;
+/// use better_routes::routes;
+/// use serde::Deserialize;
+///
+/// mod admin {
+///     use better_routes::routes;
+///     use serde::Deserialize;
+///
+///     #[derive(Deserialize)]
+///     pub struct Users;
+///
+///     async fn list_users(_: Users) {}
+///
+///     routes! {
+///         name => pub AdminRoutes,
+///         "/users" => Users {
+///             get => list_users
+///         },
+///     }
+/// }
+///
+/// #[derive(Deserialize)]
+/// struct Home;
+///
+/// async fn index(_: Home) {}
+///
+/// routes! {
+///     name => PageRoutes,
+///     "/" => Home {
+///         get => index
+///     },
+/// }
+///
+/// routes! {
+///     name => AllRoutes,
+///     merge => PageRoutes,
+///     "/admin" => nest admin::AdminRoutes,
+/// }
+///
+/// #[tokio::main]
+/// async fn main() {
+///     let r: Router = AllRoutes::routes();
+/// }
+/// ```
+///
+/// # With Conditional Routes
+///
+/// Entries and method handlers accept `#[cfg]`, `#[doc]` and `#[deprecated]` attributes.
+/// A `#[cfg]` applies to the registration of the entry or handler and to the impls generated
+/// for its path struct, so routes can be compiled only behind a Cargo feature, and the `Allow`
+/// header only lists the handlers compiled in. Handlers for the same method are only allowed
+/// when each of them has a `#[cfg]`. A `#[doc]` documents the `TypedPath` impl of the
+/// path struct. A `#[deprecated]` can only be put on a route with an inline path struct, and
+/// deprecates the struct, so its handlers and the code linking to the route get a warning.
+/// ```rust
+/// use axum::Router;
+/// use better_routes::routes;
+/// use serde::Deserialize;
+///
+/// #[derive(Deserialize)]
+/// struct Home;
+///
+/// #[cfg(debug_assertions)]
+/// #[derive(Deserialize)]
+/// struct Debug;
+///
+/// async fn index(_: Home) {}
+///
+/// #[cfg(debug_assertions)]
+/// async fn reset(_: Home) {}
+///
+/// #[cfg(debug_assertions)]
+/// async fn debug(_: Debug) {}
+///
+/// #[allow(deprecated)]
+/// async fn old_index(_: OldHome) {}
+///
+/// routes! {
+///     name => AllRoutes,
+///     /// The home page.
+///     "/" => Home {
+///         get => index,
+///         #[cfg(debug_assertions)]
+///         delete => reset,
+///     },
+///     #[cfg(debug_assertions)]
+///     "/debug" => Debug {
+///         get => debug
+///     },
+///     #[deprecated = "link to `Home` instead"]
+///     "/home" => struct OldHome {
+///         get => old_index
+///     },
+/// }
+///
+/// #[tokio::main]
+/// async fn main() {
+///     let r: Router = AllRoutes::routes();
+/// }
+/// ```
+///
+/// # With Trailing Slashes
+///
+/// `trailing_slash => strict | redirect | both`, at the top of `routes!` or in a route, sets
+/// how a route treats its path with or without a trailing slash, the other way than it is
+/// declared. `strict`, the default, only serves the declared form, `redirect` answers the other
+/// form with a `308 Permanent Redirect` to the declared one, and `both` serves both. The
+/// `Display` impl always gives the declared form.
+/// ```rust
+/// use axum::Router;
+/// use better_routes::routes;
+/// use serde::Deserialize;
+///
+/// #[derive(Deserialize)]
+/// struct Todos;
+///
+/// #[derive(Deserialize)]
+/// struct Todo {
+///     id: usize,
+/// }
+///
+/// async fn list_todos(_: Todos) {}
+///
+/// async fn get_todo(_: Todo) {}
+///
+/// routes! {
+///     name => AllRoutes,
+///     trailing_slash => redirect,
+///     "/todo/" => Todos {
+///         get => list_todos
+///     },
+///     "/todo/:id" => Todo {
+///         get => get_todo,
+///         trailing_slash => both,
+///     },
+/// }
+///
+/// #[tokio::main]
+/// async fn main() {
+///     let r: Router = AllRoutes::routes();
+/// }
+/// ```
+///
+/// # With Path Aliases
+///
+/// A route can have other paths, `"/user/:id" | "/u/:id"`, with the same captures. The first
+/// one is used by `TypedPath::PATH` and `Display`, and the others serve the same handlers, or
+/// answer with a `301 Moved Permanently` to the first one when preceded by `redirect`.
+/// ```rust
+/// use axum::Router;
+/// use better_routes::routes;
+/// use serde::Deserialize;
+///
+/// #[derive(Deserialize)]
+/// struct User {
+///     id: usize,
+/// }
+///
+/// async fn get_user(_: User) {}
+///
+/// routes! {
+///     name => AllRoutes,
+///     "/user/:id" | "/u/:id" | redirect "/users/:id" => User {
+///         get => get_user
+///     },
+/// }
+///
+/// #[tokio::main]
+/// async fn main() {
+///     let r: Router = AllRoutes::routes();
+/// }
+/// ```
+///
+/// # With Static Responses
+///
+/// Entries without a handler answer every method with a fixed response:
+/// `redirect` answers with a `308 Permanent Redirect` to a path struct, under the prefix the
+/// table is nested at, `text` with a `text/plain` body and `status` with an empty body. These
+/// entries can have plain captures, which are ignored.
+/// ```rust
+/// use axum::Router;
+/// use better_routes::routes;
+/// use serde::Deserialize;
+///
+/// #[derive(Deserialize)]
+/// struct Users;
+///
+/// async fn list_users(_: Users) {}
+///
+/// routes! {
+///     name => AllRoutes,
+///     "/users" => Users {
+///         get => list_users
+///     },
+///     "/old/users" => redirect Users,
+///     "/robots.txt" => text "User-agent: *\nDisallow:",
+///     "/ping" => status 204,
+/// }
+///
+/// #[tokio::main]
+/// async fn main() {
+///     let r: Router = AllRoutes::routes();
+/// }
+/// ```
+///
+/// # With Hosts
+///
+/// `host "api.example.com" => { ... }` serves its routes only to requests whose `Host` header
+/// matches, ignoring the port. Labels of the host can be captures, `":tenant.example.com"` or
+/// `"{tenant: String}.example.com"`, which are fields of every path struct of the block, before
+/// the path captures. With `{:#}`, the `Display` of these path structs writes the host too, as
+/// in `//acme.example.com/users/1`.
+///
+/// Host blocks are at the top of `routes!`, and take `state` and layers like groups. Blocks for
+/// different hosts can share paths, which are served by the first block matching the request
+/// and by the `fallback` of the table for other hosts, so they can't also be used outside host
+/// blocks.
+/// ```rust
+/// use axum::Router;
+/// use better_routes::routes;
+/// use serde::Deserialize;
+///
+/// #[derive(Deserialize)]
+/// struct ApiHome;
+///
+/// async fn api_home(_: ApiHome) {}
+///
+/// async fn get_user(_: TenantUser) {}
+///
+/// routes! {
+///     name => AllRoutes,
+///     host "api.example.com" => {
+///         "/" => ApiHome {
+///             get => api_home
+///         },
+///     },
+///     host "{tenant: String}.example.com" => {
+///         "/users/{id: u64}" => struct TenantUser {
+///             get => get_user
+///         },
+///     },
+/// }
+///
+/// #[tokio::main]
+/// async fn main() {
+///     let user = TenantUser { tenant: "acme".to_owned(), id: 1 };
+///     assert_eq!(user.to_string(), "/users/1");
+///     assert_eq!(format!("{user:#}"), "//acme.example.com/users/1");
+///     let r: Router = AllRoutes::routes();
+/// }
+/// ```
+///
+/// # With Layers
+///
+/// `layer` and `route_layer` can be set at the top of `routes!`, at the top of a group
+/// block or inside a route block, and only wrap the routes at that level. A `layer` also
+/// covers the `405 Method Not Allowed` responses of those routes, and at the top level the
+/// fallback too, while a `route_layer` only runs for requests that matched a handler. Each
+/// layer wraps the ones declared before it.
+/// ```rust
+/// use axum::extract::Request;
+/// use axum::http::StatusCode;
+/// use axum::middleware::{from_fn, Next};
+/// use axum::response::Response;
+/// use axum::Router;
+/// use better_routes::routes;
+/// use serde::Deserialize;
+///
+/// #[derive(Deserialize)]
+/// struct Home;
+///
+/// #[derive(Deserialize)]
+/// struct Todos;
+///
+/// async fn home(_: Home) {}
+///
+/// async fn list_todos(_: Todos) {}
+///
+/// async fn log(req: Request, next: Next) -> Response {
+///     println!("{} {}", req.method(), req.uri());
+///     next.run(req).await
+/// }
+///
+/// async fn auth(req: Request, next: Next) -> Result<Response, StatusCode> {
+///     if req.headers().contains_key("authorization") {
+///         Ok(next.run(req).await)
+///     } else {
+///         Err(StatusCode::UNAUTHORIZED)
+///     }
+/// }
+///
+/// routes! {
+///     name => AllRoutes,
+///     layer => from_fn(log),
+///     "/" => Home {
+///         get => home
+///     },
+///     "/api" => group {
+///         route_layer => from_fn(auth),
+///         "/todo" => Todos {
+///             get => list_todos
+///         },
+///     }
+/// }
+///
+/// #[tokio::main]
+/// async fn main() {
+///     let r: Router = AllRoutes::routes();
+/// }
+/// ```
+///
+/// # With Required Headers
+///
+/// `require_header "X-Name"` at the top of a group or host block, or inside a route block,
+/// serves its routes only to requests with that header, and `htmx_only` is a shorthand for
+/// `require_header "HX-Request"`. Other requests get a `400 Bad Request`, or the response of
+/// the handler after `=>`, before the path struct is extracted. Responses vary on the header.
+/// Routes can't be merged or nested inside a block requiring a header.
+/// ```rust
+/// use axum::http::StatusCode;
+/// use axum::Router;
+/// use better_routes::routes;
+/// use serde::Deserialize;
+///
+/// #[derive(Deserialize)]
+/// struct TodoRow {
+///     id: u32,
+/// }
+///
+/// #[derive(Deserialize)]
+/// struct Report;
+///
+/// async fn todo_row(_: TodoRow) {}
+///
+/// async fn report(_: Report) {}
+///
+/// async fn not_htmx() -> StatusCode {
+///     StatusCode::NOT_FOUND
+/// }
+///
+/// routes! {
+///     name => AllRoutes,
+///     "/fragments" => group {
+///         htmx_only => not_htmx,
+///         "/todo/:id" => TodoRow {
+///             get => todo_row
+///         },
+///     },
+///     "/report" => Report {
+///         get => report,
+///         require_header "X-Api-Key",
+///     }
+/// }
+///
+/// #[tokio::main]
+/// async fn main() {
+///     let r: Router = AllRoutes::routes();
+/// }
+/// ```
+///
+/// # With Guards
+///
+/// `guard => check` at the top of a group or host block, or inside a route block, serves its
+/// routes only to requests `check` accepts, before the path struct is extracted. `check` is a
+/// function or an `async` function taking `&Parts` and a reference to the state of its block,
+/// and returning `bool`. Requests it rejects fall through to the fallback of the `routes!`, of
+/// the `routes!` it's merged or nested in, or a plain `404 Not Found`. A method handler can
+/// have a guard too, as in
+/// `get(guard => check) => handler`, and then falls through to the next handler of its method
+/// and media type, if any. Routes can't be merged or nested inside a block with a guard.
+/// ```rust
+/// use axum::http::request::Parts;
+/// use axum::Router;
+/// use better_routes::routes;
+/// use serde::Deserialize;
+///
+/// #[derive(Clone)]
+/// struct AppState {
+///     beta: bool,
+/// }
+///
+/// #[derive(Deserialize)]
+/// struct Dashboard;
+///
+/// #[derive(Deserialize)]
+/// struct Preview;
+///
+/// async fn admin_dashboard(_: Dashboard) {}
+///
+/// async fn dashboard(_: Dashboard) {}
+///
+/// async fn preview(_: Preview) {}
+///
+/// fn is_admin(parts: &Parts, _: &AppState) -> bool {
+///     parts.headers.contains_key("x-admin")
+/// }
+///
+/// async fn beta_enabled(_: &Parts, state: &AppState) -> bool {
+///     state.beta
+/// }
+///
+/// routes! {
+///     name => AllRoutes,
+///     state => AppState,
+///     "/dashboard" => Dashboard {
+///         get(guard => is_admin) => admin_dashboard,
+///         get => dashboard,
+///     },
+///     "/beta" => group {
+///         guard => beta_enabled,
+///         "/preview" => Preview {
+///             get => preview
+///         },
+///     }
+/// }
+///
+/// #[tokio::main]
+/// async fn main() {
+///     let r: Router = AllRoutes::routes().with_state(AppState { beta: false });
+/// }
+/// ```
+///
 /// # With State
 /// ```rust
 /// use axum::Router;
-/// use axum_extra::routing::RouterExt;
 /// use better_routes::routes;
 /// use serde::Deserialize;
 ///
@@ -225,6 +1020,91 @@
 /// }
 /// ```
 ///
+/// # With Group and Route State
+///
+/// A group or route can declare its own `state`, which its handlers are checked against
+/// instead of the top-level one. It is extracted from the top-level state with
+/// [`FromRef`](axum::extract::FromRef) on every request, and applies to nested groups too.
+/// ```rust
+/// use axum::extract::{FromRef, State};
+/// use axum::Router;
+/// use better_routes::routes;
+/// use serde::Deserialize;
+///
+/// #[derive(Clone)]
+/// struct AppState {
+///     db: DbState,
+/// }
+///
+/// #[derive(Clone)]
+/// struct DbState;
+///
+/// impl FromRef<AppState> for DbState {
+///     fn from_ref(state: &AppState) -> Self {
+///         state.db.clone()
+///     }
+/// }
+///
+/// #[derive(Deserialize)]
+/// struct Todos;
+///
+/// async fn list_todos(_: Todos, State(db): State<DbState>) {}
+///
+/// routes! {
+///     name => AllRoutes,
+///     state => AppState,
+///     "/api" => group {
+///         state => DbState,
+///         "/todo" => Todos {
+///             get => list_todos
+///         },
+///     }
+/// }
+///
+/// #[tokio::main]
+/// async fn main() {
+///     let r: Router<AppState> = AllRoutes::routes();
+/// }
+/// ```
+///
+/// # With Fallback
+///
+/// `fallback` handles requests whose path matches no route, and is checked against the
+/// declared `state` like every other handler. Use `fallback_service` to pass a service instead.
+/// ```rust
+/// use axum::extract::State;
+/// use axum::http::StatusCode;
+/// use axum::Router;
+/// use better_routes::routes;
+/// use serde::Deserialize;
+///
+/// #[derive(Deserialize)]
+/// struct Home;
+///
+/// async fn index(_: Home) {}
+///
+/// #[derive(Clone)]
+/// struct AppState;
+///
+/// async fn not_found(_: State<AppState>) -> StatusCode {
+///     StatusCode::NOT_FOUND
+/// }
+///
+/// routes! {
+///     name => AllRoutes,
+///     state => AppState,
+///     fallback => not_found,
+///     "/" => Home {
+///         get => index
+///     }
+/// }
+///
+/// #[tokio::main]
+/// async fn main() {
+///     let r: Router<AppState> = AllRoutes::routes();
+/// }
+/// ```
+///
 /// # With Global Rejection
 /// ```rust
 /// use axum::{
@@ -233,7 +1113,6 @@
 ///     response::{IntoResponse, Response},
 ///     Router,
 /// };
-/// use axum_extra::routing::RouterExt;
 /// use better_routes::routes;
 /// use serde::Deserialize;
 ///
@@ -283,7 +1162,6 @@
 ///     response::{IntoResponse, Response},
 ///     Router,
 /// };
-/// use axum_extra::routing::RouterExt;
 /// use better_routes::routes;
 /// use serde::Deserialize;
 ///
@@ -324,3 +1202,163 @@
 /// }
 /// ```
 pub use better_routes_macros::routes;
+
+/// Define routes with attributes on the handlers of an `impl` block.
+///
+/// `#[controller]` takes the same options as [`routes!`], starting with `name`, and generates
+/// the same struct with a `routes()` method. Each associated function with a `#[get("/...")]`,
+/// `#[post("/...")]`, etc. attribute, or `#[any("/...")]`, becomes a handler, and its first
+/// argument is its path struct. Functions sharing a path struct must use the same path, and
+/// the path can be left out for structs deriving [`Path`].
+///
+/// # Example
+/// ```rust
+/// use axum::extract::State;
+/// use axum::Router;
+/// use better_routes::controller;
+/// use serde::Deserialize;
+///
+/// #[derive(Clone)]
+/// struct AppState;
+///
+/// #[derive(Deserialize)]
+/// struct Todo {
+///     id: usize,
+/// }
+///
+/// struct Todos;
+///
+/// #[controller(name => pub TodoRoutes, state => AppState)]
+/// impl Todos {
+///     #[get("/todo/:id")]
+///     async fn get_todo(todo: Todo, State(_): State<AppState>) -> String {
+///         format!("todo {}", todo.id)
+///     }
+///
+///     #[put("/todo/:id")]
+///     #[patch("/todo/:id")]
+///     async fn update_todo(_: Todo) {}
+/// }
+///
+/// #[tokio::main]
+/// async fn main() {
+///     let r: Router<AppState> = TodoRoutes::routes();
+/// }
+/// ```
+pub use better_routes_macros::controller;
+
+/// Register a handler with a route table from anywhere in the crate.
+///
+/// `#[handler(AllRoutes, get)]` adds the function to the router returned by
+/// `AllRoutes::routes()`, where `AllRoutes` is generated by [`routes!`]. The first argument of
+/// the function is its path struct, usually one deriving [`Path`]. Several methods can be
+/// listed, except `connect`. Registered routes get the top-level layers and fallback of the
+/// table, but not its `method_not_allowed` handler.
+///
+/// # Panics
+///
+/// `routes()` panics if two handlers are registered for the same method and path.
+///
+/// # Example
+/// ```rust
+/// use axum::Router;
+/// use better_routes::{handler, routes, Path};
+/// use serde::Deserialize;
+///
+/// routes! {
+///     name => AllRoutes,
+/// }
+///
+/// mod users {
+///     use better_routes::{handler, Path};
+///     use serde::Deserialize;
+///
+///     use super::AllRoutes;
+///
+///     #[derive(Deserialize, Path)]
+///     #[typed_path("/user/:id")]
+///     pub struct User {
+///         id: usize,
+///     }
+///
+///     #[handler(AllRoutes, get)]
+///     async fn get_user(user: User) -> String {
+///         format!("user {}", user.id)
+///     }
+///
+///     #[handler(AllRoutes, put, patch)]
+///     async fn update_user(_: User) {}
+/// }
+///
+/// #[tokio::main]
+/// async fn main() {
+///     let r: Router = AllRoutes::routes();
+/// }
+/// ```
+pub use better_routes_macros::handler;
+
+/// Declare the path of a path struct on the struct itself.
+///
+/// `#[derive(Path)]` implements the same `TypedPath`, `Display` and `FromRequestParts` impls
+/// that [`routes!`] generates for a path literal, from a `#[typed_path("/...")]` attribute.
+/// It isn't named `#[path]`, since that is the built-in attribute for module files, and the
+/// compiler rejects `#[path("/...")]` as a malformed use of it. The struct is then referenced in [`routes!`] without a path literal, which lets several
+/// route tables share it. A rejection can be set with `rejection = ...`, since the global and
+/// route-specific rejections of [`routes!`] don't apply to it. Generic structs get generic
+/// impls, and are referenced in [`routes!`] with the arguments their handlers use.
+///
+/// # Example
+/// ```rust
+/// use axum::extract::rejection::PathRejection;
+/// use axum::http::StatusCode;
+/// use axum::response::{IntoResponse, Response};
+/// use axum::Router;
+/// use better_routes::{routes, Path};
+/// use serde::Deserialize;
+///
+/// struct UserRejection;
+///
+/// impl From<PathRejection> for UserRejection {
+///     fn from(_: PathRejection) -> Self {
+///         Self
+///     }
+/// }
+///
+/// impl IntoResponse for UserRejection {
+///     fn into_response(self) -> Response {
+///         StatusCode::NOT_FOUND.into_response()
+///     }
+/// }
+///
+/// #[derive(Deserialize, Path)]
+/// #[typed_path("/user/:id", rejection = UserRejection)]
+/// struct User {
+///     id: usize,
+/// }
+///
+/// async fn get_user(_: User) {}
+///
+/// routes! {
+///     name => AllRoutes,
+///     User {
+///         get => get_user
+///     },
+/// }
+///
+/// #[tokio::main]
+/// async fn main() {
+///     assert_eq!(User { id: 1 }.to_string(), "/user/1");
+///     let r: Router = AllRoutes::routes();
+/// }
+/// ```
+pub use better_routes_macros::Path;
+
+pub use self::allowed_methods::AllowedMethods;
+pub use self::capture_rejection::{CaptureRejection, FailedToDeserializeCaptures, InvalidCapture};
+
+mod allowed_methods;
+mod capture_rejection;
+mod de;
+
+#[doc(hidden)]
+pub mod __private;
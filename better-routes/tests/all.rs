@@ -1,12 +1,19 @@
+use std::borrow::Cow;
+use std::convert::Infallible;
+use std::hash::Hash;
+use std::marker::PhantomData;
+
 use axum::body::Body;
 use axum::extract::rejection::PathRejection;
-use axum::extract::Request;
-use axum::http::StatusCode;
+use axum::extract::{FromRef, Request, State};
+use axum::http::request::Parts;
+use axum::http::{HeaderValue, StatusCode};
 use axum::response::{IntoResponse, Response};
-use axum_extra::routing::RouterExt;
-use better_routes::routes;
+use axum_extra::routing::TypedPath;
+use better_routes::{routes, AllowedMethods};
 use http_body_util::BodyExt;
 use serde::Deserialize;
+use tower::util::MapResponseLayer;
 use tower::ServiceExt;
 
 #[test]
@@ -310,3 +317,1806 @@ async fn should_capture_wildcard() {
     let body = post_res.into_body().collect().await.unwrap().to_bytes();
     assert_eq!(&body[..], b"hello/world");
 }
+
+#[tokio::test]
+async fn should_allow_head_options_trace_and_connect_requests() {
+    #[derive(Deserialize)]
+    struct HomePath;
+    async fn home(_: HomePath) -> &'static str {
+        "home"
+    }
+    async fn options(_: HomePath) -> &'static str {
+        "options"
+    }
+    routes! {
+        name => AllRoutes,
+        "/" => HomePath {
+            [get, head, trace, connect] => home,
+            options => options,
+        },
+    }
+    let router = AllRoutes::routes();
+    for method in ["GET", "HEAD", "TRACE", "CONNECT", "OPTIONS"] {
+        let res = router
+            .clone()
+            .oneshot(make_request("/", method))
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::OK, "{method}");
+    }
+    let head_res = router
+        .clone()
+        .oneshot(make_request("/", "HEAD"))
+        .await
+        .unwrap();
+    let body = head_res.into_body().collect().await.unwrap().to_bytes();
+    assert!(body.is_empty());
+    let options_res = router
+        .clone()
+        .oneshot(make_request("/", "OPTIONS"))
+        .await
+        .unwrap();
+    let body = options_res.into_body().collect().await.unwrap().to_bytes();
+    assert_eq!(&body[..], b"options");
+    let post_res = router
+        .clone()
+        .oneshot(make_request("/", "POST"))
+        .await
+        .unwrap();
+    assert_eq!(post_res.status(), StatusCode::METHOD_NOT_ALLOWED);
+}
+
+#[tokio::test]
+async fn should_allow_extension_methods() {
+    #[derive(Deserialize)]
+    struct CachePath {
+        key: String,
+    }
+    async fn get(cache_path: CachePath) -> String {
+        cache_path.key
+    }
+    async fn purge(cache_path: CachePath) -> String {
+        format!("purged {}", cache_path.key)
+    }
+    routes! {
+        name => AllRoutes,
+        "/cache/:key" => CachePath {
+            get => get,
+            [method "PURGE", method "BAN"] => purge,
+        },
+    }
+    let router = AllRoutes::routes();
+    let purge_res = router
+        .clone()
+        .oneshot(make_request("/cache/home", "PURGE"))
+        .await
+        .unwrap();
+    assert_eq!(purge_res.status(), StatusCode::OK);
+    let body = purge_res.into_body().collect().await.unwrap().to_bytes();
+    assert_eq!(&body[..], b"purged home");
+    let ban_res = router
+        .clone()
+        .oneshot(make_request("/cache/home", "BAN"))
+        .await
+        .unwrap();
+    assert_eq!(ban_res.status(), StatusCode::OK);
+    let get_res = router
+        .clone()
+        .oneshot(make_request("/cache/home", "GET"))
+        .await
+        .unwrap();
+    assert_eq!(get_res.status(), StatusCode::OK);
+    let propfind_res = router
+        .clone()
+        .oneshot(make_request("/cache/home", "PROPFIND"))
+        .await
+        .unwrap();
+    assert_eq!(propfind_res.status(), StatusCode::METHOD_NOT_ALLOWED);
+    assert_eq!(propfind_res.headers()["allow"], "GET,PURGE,BAN,HEAD");
+}
+
+#[tokio::test]
+async fn should_route_unclaimed_methods_to_any_handler() {
+    #[derive(Deserialize)]
+    struct GraphqlPath;
+    async fn playground(_: GraphqlPath) -> &'static str {
+        "playground"
+    }
+    async fn graphql(_: GraphqlPath, req: Request) -> String {
+        req.method().to_string()
+    }
+    routes! {
+        name => AllRoutes,
+        "/graphql" => GraphqlPath {
+            get => playground,
+            any => graphql,
+        },
+    }
+    let router = AllRoutes::routes();
+    let get_res = router
+        .clone()
+        .oneshot(make_request("/graphql", "GET"))
+        .await
+        .unwrap();
+    let body = get_res.into_body().collect().await.unwrap().to_bytes();
+    assert_eq!(&body[..], b"playground");
+    for method in ["POST", "PUT", "DELETE", "PATCH", "OPTIONS", "PURGE"] {
+        let res = router
+            .clone()
+            .oneshot(make_request("/graphql", method))
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::OK, "{method}");
+        assert!(!res.headers().contains_key("allow"));
+        let body = res.into_body().collect().await.unwrap().to_bytes();
+        assert_eq!(&body[..], method.as_bytes());
+    }
+}
+
+#[tokio::test]
+async fn should_call_method_not_allowed_handlers() {
+    #[derive(Deserialize)]
+    struct HomePath;
+    async fn home(_: HomePath) {}
+    #[derive(Deserialize)]
+    struct UserPath {
+        id: usize,
+    }
+    async fn user(_: UserPath) {}
+    async fn global_method_not_allowed(allowed: AllowedMethods) -> String {
+        format!("global: {allowed}")
+    }
+    async fn user_method_not_allowed(
+        user_path: UserPath,
+        allowed: AllowedMethods,
+    ) -> impl IntoResponse {
+        (
+            StatusCode::METHOD_NOT_ALLOWED,
+            format!("user {}: {}", user_path.id, allowed.as_str()),
+        )
+    }
+    routes! {
+        name => AllRoutes,
+        method_not_allowed => global_method_not_allowed,
+        "/" => HomePath {
+            get => home,
+        },
+        "/user/:id" => UserPath {
+            [put, method "PURGE"] => user,
+            method_not_allowed => user_method_not_allowed,
+        },
+    }
+    let router = AllRoutes::routes();
+    let post_res = router
+        .clone()
+        .oneshot(make_request("/", "POST"))
+        .await
+        .unwrap();
+    assert_eq!(post_res.status(), StatusCode::OK);
+    assert_eq!(post_res.headers()["allow"], "GET,HEAD");
+    let body = post_res.into_body().collect().await.unwrap().to_bytes();
+    assert_eq!(&body[..], b"global: GET, HEAD");
+    let user_get_res = router
+        .clone()
+        .oneshot(make_request("/user/7", "GET"))
+        .await
+        .unwrap();
+    assert_eq!(user_get_res.status(), StatusCode::METHOD_NOT_ALLOWED);
+    assert_eq!(user_get_res.headers()["allow"], "PUT,PURGE");
+    let body = user_get_res.into_body().collect().await.unwrap().to_bytes();
+    assert_eq!(&body[..], b"user 7: PUT,PURGE");
+    let user_put_res = router
+        .clone()
+        .oneshot(make_request("/user/7", "PUT"))
+        .await
+        .unwrap();
+    assert_eq!(user_put_res.status(), StatusCode::OK);
+}
+
+#[tokio::test]
+async fn should_call_fallback_for_unmatched_paths() {
+    #[derive(Clone)]
+    struct AppState {
+        not_found: &'static str,
+    }
+    #[derive(Deserialize)]
+    struct HomePath;
+    async fn home(_: HomePath) {}
+    async fn not_found(State(state): State<AppState>) -> (StatusCode, &'static str) {
+        (StatusCode::NOT_FOUND, state.not_found)
+    }
+    routes! {
+        name => AllRoutes,
+        state => AppState,
+        fallback => not_found,
+        "/" => HomePath {
+            get => home,
+        },
+    }
+    let router = AllRoutes::routes().with_state(AppState {
+        not_found: "nothing here",
+    });
+    let home_res = router
+        .clone()
+        .oneshot(make_request("/", "GET"))
+        .await
+        .unwrap();
+    assert_eq!(home_res.status(), StatusCode::OK);
+    let missing_res = router
+        .clone()
+        .oneshot(make_request("/missing", "GET"))
+        .await
+        .unwrap();
+    assert_eq!(missing_res.status(), StatusCode::NOT_FOUND);
+    let body = missing_res.into_body().collect().await.unwrap().to_bytes();
+    assert_eq!(&body[..], b"nothing here");
+}
+
+#[tokio::test]
+async fn should_call_fallback_service_for_unmatched_paths() {
+    #[derive(Deserialize)]
+    struct HomePath;
+    async fn home(_: HomePath) {}
+    routes! {
+        name => AllRoutes,
+        fallback_service => tower::service_fn(|req: Request| async move {
+            Ok::<_, Infallible>(req.uri().path().to_owned().into_response())
+        }),
+        "/" => HomePath {
+            get => home,
+        },
+    }
+    let router = AllRoutes::routes();
+    let missing_res = router
+        .clone()
+        .oneshot(make_request("/static/app.js", "GET"))
+        .await
+        .unwrap();
+    let body = missing_res.into_body().collect().await.unwrap().to_bytes();
+    assert_eq!(&body[..], b"/static/app.js");
+}
+
+#[tokio::test]
+async fn should_prefix_routes_in_groups() {
+    #[derive(Deserialize)]
+    struct ApiPath;
+    async fn api(_: ApiPath) -> &'static str {
+        "api"
+    }
+    #[derive(Deserialize)]
+    struct TodoPath;
+    async fn todos(_: TodoPath) -> &'static str {
+        "todos"
+    }
+    #[derive(Deserialize)]
+    struct TodoWithIdPath {
+        version: usize,
+        id: usize,
+    }
+    async fn todo(todo_path: TodoWithIdPath) -> String {
+        format!("todo {} from {}", todo_path.id, todo_path.version)
+    }
+    routes! {
+        name => AllRoutes,
+        "/api" => group {
+            "/" => ApiPath {
+                get => api,
+            },
+            "/todo" => TodoPath {
+                get => todos,
+            },
+            "/:version" => group {
+                "/todo/:id" => TodoWithIdPath {
+                    get => todo,
+                },
+            },
+        },
+    }
+    assert_eq!(<ApiPath as TypedPath>::PATH, "/api");
+    assert_eq!(<TodoPath as TypedPath>::PATH, "/api/todo");
+    assert_eq!(
+        <TodoWithIdPath as TypedPath>::PATH,
+        "/api/:version/todo/:id"
+    );
+    assert_eq!(
+        TodoWithIdPath { version: 2, id: 7 }.to_string(),
+        "/api/2/todo/7"
+    );
+    let router = AllRoutes::routes();
+    for (path, expected) in [
+        ("/api", &b"api"[..]),
+        ("/api/todo", b"todos"),
+        ("/api/2/todo/7", b"todo 7 from 2"),
+    ] {
+        let res = router
+            .clone()
+            .oneshot(make_request(path, "GET"))
+            .await
+            .unwrap();
+        let body = res.into_body().collect().await.unwrap().to_bytes();
+        assert_eq!(&body[..], expected);
+    }
+    let unprefixed_res = router
+        .clone()
+        .oneshot(make_request("/todo", "GET"))
+        .await
+        .unwrap();
+    assert_eq!(unprefixed_res.status(), StatusCode::NOT_FOUND);
+}
+
+fn tag(name: &'static str) -> MapResponseLayer<impl Fn(Response) -> Response + Clone> {
+    MapResponseLayer::new(move |mut res: Response| {
+        res.headers_mut()
+            .append("x-layer", HeaderValue::from_static(name));
+        res
+    })
+}
+
+#[tokio::test]
+async fn should_apply_layers_in_order() {
+    #[derive(Deserialize)]
+    struct HomePath;
+    async fn home(_: HomePath) {}
+    #[derive(Deserialize)]
+    struct TodoPath;
+    async fn todos(_: TodoPath) {}
+    async fn purge(_: TodoPath) {}
+    routes! {
+        name => AllRoutes,
+        layer => tag("top"),
+        "/" => HomePath {
+            route_layer => tag("matched"),
+            get => home,
+        },
+        "/api" => group {
+            layer => tag("api"),
+            "/todo" => TodoPath {
+                layer => tag("route"),
+                route_layer => tag("matched"),
+                get => todos,
+                method "PURGE" => purge,
+            },
+        },
+    }
+    let router = AllRoutes::routes();
+    for (path, method, status, expected) in [
+        ("/", "GET", StatusCode::OK, &["matched", "top"][..]),
+        ("/", "POST", StatusCode::METHOD_NOT_ALLOWED, &["top"]),
+        ("/missing", "GET", StatusCode::NOT_FOUND, &["top"]),
+        (
+            "/api/todo",
+            "GET",
+            StatusCode::OK,
+            &["route", "matched", "api", "top"],
+        ),
+        (
+            "/api/todo",
+            "PURGE",
+            StatusCode::OK,
+            &["route", "matched", "api", "top"],
+        ),
+        (
+            "/api/todo",
+            "POST",
+            StatusCode::METHOD_NOT_ALLOWED,
+            &["route", "api", "top"],
+        ),
+    ] {
+        let res = router
+            .clone()
+            .oneshot(make_request(path, method))
+            .await
+            .unwrap();
+        assert_eq!(res.status(), status, "{method} {path}");
+        let layers = res
+            .headers()
+            .get_all("x-layer")
+            .iter()
+            .map(|value| value.to_str().unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(layers, expected, "{method} {path}");
+    }
+}
+
+#[tokio::test]
+async fn should_extract_group_and_route_state_from_app_state() {
+    #[derive(Clone)]
+    struct AppState {
+        db: DbState,
+        admin: AdminState,
+    }
+    #[derive(Clone)]
+    struct DbState(&'static str);
+    #[derive(Clone)]
+    struct AdminState(&'static str);
+    impl FromRef<AppState> for DbState {
+        fn from_ref(state: &AppState) -> Self {
+            state.db.clone()
+        }
+    }
+    impl FromRef<AppState> for AdminState {
+        fn from_ref(state: &AppState) -> Self {
+            state.admin.clone()
+        }
+    }
+    #[derive(Deserialize)]
+    struct TodoPath;
+    async fn todos(_: TodoPath, State(db): State<DbState>) -> &'static str {
+        db.0
+    }
+    #[derive(Deserialize)]
+    struct AdminPath;
+    async fn admin(_: AdminPath, State(admin): State<AdminState>) -> &'static str {
+        admin.0
+    }
+    async fn method_not_allowed(State(state): State<AppState>) -> &'static str {
+        state.db.0
+    }
+    routes! {
+        name => AllRoutes,
+        state => AppState,
+        method_not_allowed => method_not_allowed,
+        "/api" => group {
+            state => DbState,
+            "/todo" => TodoPath {
+                get => todos,
+            },
+            "/admin" => AdminPath {
+                state => AdminState,
+                get => admin,
+            },
+        },
+    }
+    let router = AllRoutes::routes().with_state(AppState {
+        db: DbState("db"),
+        admin: AdminState("admin"),
+    });
+    for (path, method, expected) in [
+        ("/api/todo", "GET", &b"db"[..]),
+        ("/api/admin", "GET", b"admin"),
+        ("/api/admin", "POST", b"db"),
+    ] {
+        let res = router
+            .clone()
+            .oneshot(make_request(path, method))
+            .await
+            .unwrap();
+        let body = res.into_body().collect().await.unwrap().to_bytes();
+        assert_eq!(&body[..], expected, "{method} {path}");
+    }
+}
+
+#[tokio::test]
+async fn should_merge_and_nest_other_routes() {
+    #[derive(Deserialize)]
+    struct HomePath;
+    async fn home(_: HomePath) -> &'static str {
+        "home"
+    }
+    #[derive(Deserialize)]
+    struct UsersPath;
+    async fn users(_: UsersPath) -> &'static str {
+        "users"
+    }
+    #[derive(Deserialize)]
+    struct AboutPath;
+    async fn about(_: AboutPath) -> &'static str {
+        "about"
+    }
+    routes! {
+        name => AdminRoutes,
+        layer => tag("admin"),
+        "/users" => UsersPath {
+            get => users,
+        },
+    }
+    routes! {
+        name => PageRoutes,
+        "/about" => AboutPath {
+            get => about,
+        },
+    }
+    routes! {
+        name => AllRoutes,
+        "/" => HomePath {
+            get => home,
+        },
+        merge => PageRoutes,
+        "/admin" => nest AdminRoutes,
+        "/api" => group {
+            "/v1" => nest AdminRoutes,
+        },
+    }
+    let router = AllRoutes::routes();
+    for (path, expected) in [
+        ("/", &b"home"[..]),
+        ("/about", b"about"),
+        ("/admin/users", b"users"),
+        ("/api/v1/users", b"users"),
+    ] {
+        let res = router
+            .clone()
+            .oneshot(make_request(path, "GET"))
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::OK, "{path}");
+        let body = res.into_body().collect().await.unwrap().to_bytes();
+        assert_eq!(&body[..], expected, "{path}");
+    }
+    let not_found_res = router
+        .clone()
+        .oneshot(make_request("/users", "GET"))
+        .await
+        .unwrap();
+    assert_eq!(not_found_res.status(), StatusCode::NOT_FOUND);
+    assert!(not_found_res.headers().get("x-layer").is_none());
+}
+
+#[tokio::test]
+async fn should_route_derived_path_structs() {
+    struct UserRejection;
+    impl From<PathRejection> for UserRejection {
+        fn from(_: PathRejection) -> Self {
+            Self
+        }
+    }
+    impl IntoResponse for UserRejection {
+        fn into_response(self) -> Response {
+            (StatusCode::NOT_FOUND, "no such user").into_response()
+        }
+    }
+    #[derive(Deserialize, better_routes::Path)]
+    #[typed_path("/user/:id", rejection = UserRejection)]
+    struct UserPath {
+        id: usize,
+    }
+    async fn user(user_path: UserPath) -> String {
+        format!("user {}", user_path.id)
+    }
+    async fn admin_user(user_path: UserPath) -> String {
+        format!("admin user {}", user_path.id)
+    }
+    routes! {
+        name => UserRoutes,
+        UserPath {
+            get => user,
+        },
+    }
+    routes! {
+        name => AdminRoutes,
+        UserPath {
+            get => admin_user,
+        },
+    }
+    assert_eq!(<UserPath as TypedPath>::PATH, "/user/:id");
+    assert_eq!(UserPath { id: 7 }.to_string(), "/user/7");
+    for (router, path, expected) in [
+        (UserRoutes::routes(), "/user/7", &b"user 7"[..]),
+        (AdminRoutes::routes(), "/user/7", b"admin user 7"),
+        (UserRoutes::routes(), "/user/x", b"no such user"),
+    ] {
+        let res = router.oneshot(make_request(path, "GET")).await.unwrap();
+        let body = res.into_body().collect().await.unwrap().to_bytes();
+        assert_eq!(&body[..], expected);
+    }
+}
+
+#[tokio::test]
+async fn should_only_compile_routes_and_handlers_enabled_by_cfg() {
+    #[derive(Deserialize)]
+    struct HomePath;
+    #[cfg(any())]
+    #[derive(Deserialize)]
+    struct DebugPath;
+    #[cfg(any())]
+    #[derive(Deserialize)]
+    struct AdminPath;
+    async fn home(_: HomePath) -> &'static str {
+        "home"
+    }
+    async fn create(_: HomePath) -> &'static str {
+        "create"
+    }
+    #[cfg(any())]
+    async fn delete(_: HomePath) {}
+    #[cfg(any())]
+    async fn debug(_: DebugPath) {}
+    #[cfg(any())]
+    async fn admin(_: AdminPath) {}
+    routes! {
+        name => AllRoutes,
+        /// The home page.
+        "/" => HomePath {
+            get => home,
+            #[cfg(not(any()))]
+            post => create,
+            #[cfg(any())]
+            delete => delete,
+            #[cfg(any())]
+            method "PURGE" => delete,
+        },
+        #[cfg(any())]
+        "/debug" => DebugPath {
+            get => debug,
+        },
+        #[cfg(any())]
+        "/admin" => group {
+            "/" => AdminPath {
+                get => admin,
+            },
+        },        "/staff" => group {
+            route_layer => tag("route"),
+            #[cfg(any())]
+            "/" => AdminPath {
+                get => admin,
+            },
+        },
+    }
+    for (path, method, status, allow) in [
+        ("/", "GET", StatusCode::OK, None),
+        ("/", "POST", StatusCode::OK, None),
+        (
+            "/",
+            "DELETE",
+            StatusCode::METHOD_NOT_ALLOWED,
+            Some("GET,POST,HEAD"),
+        ),
+        (
+            "/",
+            "PURGE",
+            StatusCode::METHOD_NOT_ALLOWED,
+            Some("GET,POST,HEAD"),
+        ),
+        ("/debug", "GET", StatusCode::NOT_FOUND, None),
+        ("/admin", "GET", StatusCode::NOT_FOUND, None),
+        ("/staff", "GET", StatusCode::NOT_FOUND, None),
+    ] {
+        let res = AllRoutes::routes()
+            .oneshot(make_request(path, method))
+            .await
+            .unwrap();
+        assert_eq!(res.status(), status, "{method} {path}");
+        assert_eq!(
+            res.headers()
+                .get("allow")
+                .map(|allow| allow.to_str().unwrap()),
+            allow,
+            "{method} {path}"
+        );
+    }
+}
+
+#[tokio::test]
+async fn should_route_generic_path_structs() {
+    trait Kind: Send + 'static {
+        const NAME: &'static str;
+    }
+    struct User;
+    impl Kind for User {
+        const NAME: &'static str = "user";
+    }
+    #[derive(Deserialize)]
+    struct ItemPath<K> {
+        id: usize,
+        #[serde(skip)]
+        kind: PhantomData<K>,
+    }
+    #[derive(Deserialize, better_routes::Path)]
+    #[typed_path("/search/:query")]
+    struct SearchPath<'a> {
+        query: Cow<'a, str>,
+    }
+    async fn item<K: Kind>(item_path: ItemPath<K>) -> String {
+        format!("{} {}", K::NAME, item_path.id)
+    }
+    async fn search(search_path: SearchPath<'static>) -> String {
+        format!("search {}", search_path.query)
+    }
+    routes! {
+        name => AllRoutes,
+        "/items/:id" => ItemPath<K: Kind> {
+            get => item::<User>,
+        },
+        SearchPath<'static> {
+            get => search,
+        },
+    }
+    assert_eq!(<ItemPath<User> as TypedPath>::PATH, "/items/:id");
+    let item_path = ItemPath::<User> {
+        id: 7,
+        kind: PhantomData,
+    };
+    assert_eq!(item_path.to_string(), "/items/7");
+    let search_path = SearchPath {
+        query: Cow::Borrowed("a b"),
+    };
+    assert_eq!(search_path.to_string(), "/search/a%20b");
+    for (path, expected) in [
+        ("/items/7", &b"user 7"[..]),
+        ("/search/rust", b"search rust"),
+    ] {
+        let res = AllRoutes::routes()
+            .oneshot(make_request(path, "GET"))
+            .await
+            .unwrap();
+        let body = res.into_body().collect().await.unwrap().to_bytes();
+        assert_eq!(&body[..], expected);
+    }
+}
+
+#[tokio::test]
+async fn should_route_tuple_path_structs() {
+    #[derive(Deserialize)]
+    struct UserPath(u64);
+    #[derive(Deserialize)]
+    struct PairPath(String, u32);
+    #[derive(Deserialize, better_routes::Path)]
+    #[typed_path("/post/:id")]
+    struct PostPath(u64);
+    async fn user(UserPath(id): UserPath) -> String {
+        format!("user {id}")
+    }
+    async fn pair(PairPath(name, count): PairPath) -> String {
+        format!("pair {name} {count}")
+    }
+    async fn post(PostPath(id): PostPath) -> String {
+        format!("post {id}")
+    }
+    routes! {
+        name => AllRoutes,
+        "/user/:id" => UserPath(..) {
+            get => user,
+        },
+        "/pair/:name/:count" => PairPath(..) {
+            get => pair,
+        },
+        PostPath {
+            get => post,
+        },
+    }
+    assert_eq!(UserPath(7).to_string(), "/user/7");
+    assert_eq!(PairPath("a b".to_owned(), 2).to_string(), "/pair/a%20b/2");
+    assert_eq!(PostPath(3).to_string(), "/post/3");
+    for (path, expected) in [
+        ("/user/7", &b"user 7"[..]),
+        ("/pair/a/2", b"pair a 2"),
+        ("/post/3", b"post 3"),
+    ] {
+        let res = AllRoutes::routes()
+            .oneshot(make_request(path, "GET"))
+            .await
+            .unwrap();
+        let body = res.into_body().collect().await.unwrap().to_bytes();
+        assert_eq!(&body[..], expected);
+    }
+}
+
+#[tokio::test]
+async fn should_define_inline_path_structs() {
+    async fn user(user_path: UserPath) -> String {
+        format!("user {}", user_path.id)
+    }
+    async fn post(PostPath(user, id): PostPath) -> String {
+        format!("post {user} {id}")
+    }
+    routes! {
+        name => AllRoutes,
+        "/user/:id" => pub UserPath { pub id: u64 } {
+            get => user,
+        },
+        "/user/:user/post/:id" => PostPath(u64, u64) {
+            get => post,
+        },
+    }
+    let user_path = UserPath { id: 7 };
+    assert_eq!(user_path.clone(), UserPath { id: 7 });
+    assert_eq!(format!("{user_path:?}"), "UserPath { id: 7 }");
+    assert_eq!(user_path.to_string(), "/user/7");
+    assert_eq!(PostPath(1, 2).to_string(), "/user/1/post/2");
+    fn assert_hash<T: Hash>(_: &T) {}
+    assert_hash(&PostPath(1, 2));
+    for (path, expected) in [("/user/7", &b"user 7"[..]), ("/user/1/post/2", b"post 1 2")] {
+        let res = AllRoutes::routes()
+            .oneshot(make_request(path, "GET"))
+            .await
+            .unwrap();
+        let body = res.into_body().collect().await.unwrap().to_bytes();
+        assert_eq!(&body[..], expected);
+    }
+}
+
+#[tokio::test]
+async fn should_route_typed_captures() {
+    #[derive(Deserialize)]
+    struct PostPath {
+        user: u64,
+        slug: String,
+    }
+    #[derive(Deserialize, better_routes::Path)]
+    #[typed_path("/file/{*path: String}")]
+    struct FilePath {
+        path: String,
+    }
+    async fn user(user_path: UserPath) -> String {
+        format!("user {}", user_path.id)
+    }
+    async fn post(post_path: PostPath) -> String {
+        format!("post {} {}", post_path.user, post_path.slug)
+    }
+    async fn file(file_path: FilePath) -> String {
+        format!("file {}", file_path.path)
+    }
+    routes! {
+        name => AllRoutes,
+        "/user/{id: u64}" => struct UserPath {
+            get => user,
+        },
+        "/user/{user: u64}/post/{slug: String}" => PostPath {
+            get => post,
+        },
+        FilePath {
+            get => file,
+        },
+    }
+    assert_eq!(<UserPath as TypedPath>::PATH, "/user/:id");
+    assert_eq!(<PostPath as TypedPath>::PATH, "/user/:user/post/:slug");
+    assert_eq!(<FilePath as TypedPath>::PATH, "/file/*path");
+    assert_eq!(UserPath { id: 7 }.to_string(), "/user/7");
+    for (path, status, expected) in [
+        ("/user/7", StatusCode::OK, &b"user 7"[..]),
+        ("/user/1/post/hello", StatusCode::OK, b"post 1 hello"),
+        ("/file/a/b", StatusCode::OK, b"file a/b"),
+        ("/user/x", StatusCode::BAD_REQUEST, b""),
+    ] {
+        let res = AllRoutes::routes()
+            .oneshot(make_request(path, "GET"))
+            .await
+            .unwrap();
+        assert_eq!(res.status(), status);
+        if status == StatusCode::OK {
+            let body = res.into_body().collect().await.unwrap().to_bytes();
+            assert_eq!(&body[..], expected);
+        }
+    }
+}
+
+#[tokio::test]
+async fn should_reject_captures_not_matching_their_constraint() {
+    struct PageRejection(String);
+    impl From<PathRejection> for PageRejection {
+        fn from(rejection: PathRejection) -> Self {
+            Self(rejection.body_text())
+        }
+    }
+    impl From<better_routes::InvalidCapture> for PageRejection {
+        fn from(invalid: better_routes::InvalidCapture) -> Self {
+            Self(format!("no page {}", invalid.value()))
+        }
+    }
+    impl IntoResponse for PageRejection {
+        fn into_response(self) -> Response {
+            (StatusCode::NOT_FOUND, self.0).into_response()
+        }
+    }
+    #[derive(Deserialize)]
+    struct UserPath {
+        id: String,
+    }
+    #[derive(Deserialize)]
+    struct PagePath {
+        page: i32,
+    }
+    async fn user(user_path: UserPath) -> String {
+        format!("user {}", user_path.id)
+    }
+    async fn page(page_path: PagePath) -> String {
+        format!("page {}", page_path.page)
+    }
+    async fn post(post_path: PostPath) -> String {
+        format!("post {}", post_path.slug)
+    }
+    routes! {
+        name => AllRoutes,
+        r"/user/:id<\d+>" => UserPath {
+            get => user,
+        },
+        "/page/:page<-1..=1000>" => rejection PageRejection => PagePath {
+            get => page,
+        },
+        "/post/{slug<[a-z0-9-]+>: String}" => struct PostPath {
+            get => post,
+        },
+    }
+    assert_eq!(<UserPath as TypedPath>::PATH, "/user/:id");
+    for (path, status, expected) in [
+        ("/user/42", StatusCode::OK, "user 42"),
+        (
+            "/user/4a",
+            StatusCode::BAD_REQUEST,
+            r"Invalid URL: `4a` doesn't match `\d+` for `id`",
+        ),
+        ("/page/-1", StatusCode::OK, "page -1"),
+        ("/page/1001", StatusCode::NOT_FOUND, "no page 1001"),
+        ("/page/x", StatusCode::NOT_FOUND, "no page x"),
+        ("/post/hello-world", StatusCode::OK, "post hello-world"),
+        (
+            "/post/Hello",
+            StatusCode::BAD_REQUEST,
+            "Invalid URL: `Hello` doesn't match `[a-z0-9-]+` for `slug`",
+        ),
+    ] {
+        let res = AllRoutes::routes()
+            .oneshot(make_request(path, "GET"))
+            .await
+            .unwrap();
+        assert_eq!(res.status(), status, "{path}");
+        let body = res.into_body().collect().await.unwrap().to_bytes();
+        assert_eq!(&body[..], expected.as_bytes(), "{path}");
+    }
+}
+
+#[tokio::test]
+async fn should_apply_trailing_slash_policies() {
+    #[derive(Deserialize)]
+    struct TodosPath;
+    #[derive(Deserialize)]
+    struct TodoPath {
+        id: usize,
+    }
+    #[derive(Deserialize)]
+    struct UsersPath;
+    async fn todos(_: TodosPath) -> &'static str {
+        "todos"
+    }
+    async fn todo(todo_path: TodoPath) -> String {
+        format!("todo {}", todo_path.id)
+    }
+    async fn users(_: UsersPath) -> &'static str {
+        "users"
+    }
+    routes! {
+        name => AllRoutes,
+        trailing_slash => redirect,
+        "/todo/" => TodosPath {
+            get => todos,
+        },
+        "/todo/:id" => TodoPath {
+            get => todo,
+            trailing_slash => both,
+        },
+        "/users" => UsersPath {
+            get => users,
+            trailing_slash => strict,
+        },
+    }
+    assert_eq!(TodosPath.to_string(), "/todo/");
+    for (path, status, location, body) in [
+        ("/todo/", StatusCode::OK, None, "todos"),
+        (
+            "/todo?done=1",
+            StatusCode::PERMANENT_REDIRECT,
+            Some("/todo/?done=1"),
+            "",
+        ),
+        ("/todo/7", StatusCode::OK, None, "todo 7"),
+        ("/todo/7/", StatusCode::OK, None, "todo 7"),
+        ("/users", StatusCode::OK, None, "users"),
+        ("/users/", StatusCode::NOT_FOUND, None, ""),
+    ] {
+        let res = AllRoutes::routes()
+            .oneshot(make_request(path, "GET"))
+            .await
+            .unwrap();
+        assert_eq!(res.status(), status, "{path}");
+        assert_eq!(
+            res.headers()
+                .get("location")
+                .map(|location| location.to_str().unwrap()),
+            location,
+            "{path}"
+        );
+        let bytes = res.into_body().collect().await.unwrap().to_bytes();
+        assert_eq!(&bytes[..], body.as_bytes(), "{path}");
+    }
+}
+
+#[tokio::test]
+async fn should_serve_and_redirect_path_aliases() {
+    #[derive(Deserialize)]
+    struct UserPath {
+        id: usize,
+    }
+    #[derive(Deserialize)]
+    struct FilePath {
+        path: String,
+    }
+    async fn user(user_path: UserPath) -> String {
+        format!("user {}", user_path.id)
+    }
+    async fn file(file_path: FilePath) -> String {
+        format!("file {}", file_path.path)
+    }
+    routes! {
+        name => UserRoutes,
+        "/user/:id" | "/u/:id" | redirect "/users/:id" => UserPath {
+            get => user,
+        },
+        "/files/*path" | redirect "/f/*path" => FilePath {
+            get => file,
+        },
+    }
+    routes! {
+        name => AllRoutes,
+        "/api" => nest UserRoutes,
+    }
+    assert_eq!(<UserPath as TypedPath>::PATH, "/user/:id");
+    assert_eq!(UserPath { id: 7 }.to_string(), "/user/7");
+    for (path, status, location, body) in [
+        ("/api/user/7", StatusCode::OK, None, "user 7"),
+        ("/api/u/7", StatusCode::OK, None, "user 7"),
+        (
+            "/api/users/7?tab=posts",
+            StatusCode::MOVED_PERMANENTLY,
+            Some("/api/user/7?tab=posts"),
+            "",
+        ),
+        (
+            "/api/f/a%20b/c",
+            StatusCode::MOVED_PERMANENTLY,
+            Some("/api/files/a%20b/c"),
+            "",
+        ),
+    ] {
+        let res = AllRoutes::routes()
+            .oneshot(make_request(path, "GET"))
+            .await
+            .unwrap();
+        assert_eq!(res.status(), status, "{path}");
+        assert_eq!(
+            res.headers()
+                .get("location")
+                .map(|location| location.to_str().unwrap()),
+            location,
+            "{path}"
+        );
+        let bytes = res.into_body().collect().await.unwrap().to_bytes();
+        assert_eq!(&bytes[..], body.as_bytes(), "{path}");
+    }
+}
+
+#[tokio::test]
+async fn should_serve_static_responses() {
+    #[derive(Deserialize)]
+    struct UserPath {
+        id: usize,
+    }
+    async fn user(user_path: UserPath) -> String {
+        format!("user {}", user_path.id)
+    }
+    routes! {
+        name => AllRoutes,
+        "/user/:id" => UserPath {
+            get => user,
+        },
+        "/me" => redirect UserPath { id: 1 },
+        "/legacy" => group {
+            "/files/*path" => status 410,
+        },
+        "/robots.txt" => text "User-agent: *",
+        "/ping" => status 204,
+    }
+    for (path, method, status, location, body) in [
+        (
+            "/me",
+            "GET",
+            StatusCode::PERMANENT_REDIRECT,
+            Some("/user/1"),
+            "",
+        ),
+        (
+            "/me",
+            "POST",
+            StatusCode::PERMANENT_REDIRECT,
+            Some("/user/1"),
+            "",
+        ),
+        ("/legacy/files/a/b", "GET", StatusCode::GONE, None, ""),
+        ("/robots.txt", "GET", StatusCode::OK, None, "User-agent: *"),
+        ("/ping", "GET", StatusCode::NO_CONTENT, None, ""),
+    ] {
+        let res = AllRoutes::routes()
+            .oneshot(make_request(path, method))
+            .await
+            .unwrap();
+        assert_eq!(res.status(), status, "{method} {path}");
+        assert_eq!(
+            res.headers()
+                .get("location")
+                .map(|location| location.to_str().unwrap()),
+            location,
+            "{method} {path}"
+        );
+        let bytes = res.into_body().collect().await.unwrap().to_bytes();
+        assert_eq!(&bytes[..], body.as_bytes(), "{method} {path}");
+    }
+    routes! {
+        name => NestedRoutes,
+        "/api" => nest AllRoutes,
+    }
+    let res = NestedRoutes::routes()
+        .oneshot(make_request("/api/me", "GET"))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::PERMANENT_REDIRECT);
+    assert_eq!(res.headers()["location"], "/api/user/1");
+}
+
+#[tokio::test]
+async fn should_route_by_host() {
+    #[derive(Deserialize)]
+    struct ApiHomePath;
+    async fn home(home: TenantHome) -> String {
+        format!("{} home", home.tenant)
+    }
+    async fn api_home(_: ApiHomePath) -> &'static str {
+        "api"
+    }
+    async fn tenant_user(user: TenantUser) -> String {
+        format!("{} {}", user.tenant, user.id)
+    }
+    async fn fallback() -> (StatusCode, &'static str) {
+        (StatusCode::NOT_FOUND, "fallback")
+    }
+    routes! {
+        name => AllRoutes,
+        fallback => fallback,
+        host "api.example.com" => {
+            "/" => ApiHomePath {
+                get => api_home,
+            },
+        },
+        host "{tenant: String}.example.com" => {
+            "/" => struct TenantHome {
+                get => home,
+            },
+            "/users" => group {
+                "/{id: u64}" => struct TenantUser {
+                    get => tenant_user,
+                },
+            },
+        },
+    }
+    assert_eq!(<TenantUser as TypedPath>::PATH, "/users/:id");
+    let user = TenantUser {
+        tenant: "acme".to_owned(),
+        id: 7,
+    };
+    assert_eq!(user.to_string(), "/users/7");
+    assert_eq!(format!("{user:#}"), "//acme.example.com/users/7");
+    for (host, path, status, body) in [
+        ("api.example.com", "/", StatusCode::OK, "api"),
+        ("acme.example.com:3000", "/", StatusCode::OK, "acme home"),
+        ("Acme.Example.com", "/users/7", StatusCode::OK, "acme 7"),
+        ("api.example.com", "/users/7", StatusCode::OK, "api 7"),
+        ("example.com", "/", StatusCode::NOT_FOUND, "fallback"),
+        ("example.com", "/users/7", StatusCode::NOT_FOUND, "fallback"),
+    ] {
+        let req = Request::builder()
+            .uri(path)
+            .header("host", host)
+            .body(Body::empty())
+            .unwrap();
+        let res = AllRoutes::routes().oneshot(req).await.unwrap();
+        assert_eq!(res.status(), status, "{host} {path}");
+        let bytes = res.into_body().collect().await.unwrap().to_bytes();
+        assert_eq!(&bytes[..], body.as_bytes(), "{host} {path}");
+    }
+}
+
+#[tokio::test]
+async fn should_choose_handlers_by_accept() {
+    #[derive(Deserialize)]
+    struct TodosPath;
+    #[derive(Deserialize)]
+    struct TodoPath {
+        id: usize,
+    }
+    async fn todos_html(_: TodosPath) -> &'static str {
+        "html"
+    }
+    async fn todos_json(_: TodosPath) -> &'static str {
+        "json"
+    }
+    async fn todo_json(todo_path: TodoPath) -> String {
+        format!("json {}", todo_path.id)
+    }
+    async fn todo_row(todo_path: TodoPath) -> String {
+        format!("row {}", todo_path.id)
+    }
+    async fn not_acceptable(_: TodoPath) -> (StatusCode, &'static str) {
+        (StatusCode::NOT_ACCEPTABLE, "json only")
+    }
+    routes! {
+        name => AllRoutes,
+        "/todos" => TodosPath {
+            get(html) => todos_html,
+            get(json) => todos_json,
+        },
+        "/todos/:id" => TodoPath {
+            get(accept => json) => todo_json,
+            post(accept => html) => todo_row,
+            post(accept => json) => todo_json,
+            not_acceptable => not_acceptable,
+        },
+    }
+    for (path, accept, status, body) in [
+        ("/todos", None, StatusCode::OK, "html"),
+        ("/todos", Some("application/json"), StatusCode::OK, "json"),
+        ("/todos", Some("text/*"), StatusCode::OK, "html"),
+        (
+            "/todos",
+            Some("text/html;q=0.5, application/*"),
+            StatusCode::OK,
+            "json",
+        ),
+        (
+            "/todos",
+            Some("*/*;q=0.1, text/html"),
+            StatusCode::OK,
+            "html",
+        ),
+        ("/todos", Some("image/png"), StatusCode::NOT_ACCEPTABLE, ""),
+        (
+            "/todos/1",
+            Some("application/json"),
+            StatusCode::OK,
+            "json 1",
+        ),
+        (
+            "/todos/1",
+            Some("application/json;q=0, */*"),
+            StatusCode::NOT_ACCEPTABLE,
+            "json only",
+        ),
+    ] {
+        let mut req = make_request(path, "GET");
+        if let Some(accept) = accept {
+            req.headers_mut()
+                .insert("accept", HeaderValue::from_static(accept));
+        }
+        let res = AllRoutes::routes().oneshot(req).await.unwrap();
+        assert_eq!(res.status(), status, "{path} {accept:?}");
+        assert_eq!(res.headers().get("vary").unwrap(), "accept");
+        let bytes = res.into_body().collect().await.unwrap().to_bytes();
+        assert_eq!(&bytes[..], body.as_bytes(), "{path} {accept:?}");
+    }
+    for (accept, body) in [
+        (None, "row 1"),
+        (Some("text/html"), "row 1"),
+        (Some("application/json"), "json 1"),
+    ] {
+        let mut req = make_request("/todos/1", "POST");
+        if let Some(accept) = accept {
+            req.headers_mut()
+                .insert("accept", HeaderValue::from_static(accept));
+        }
+        let res = AllRoutes::routes().oneshot(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::OK, "{accept:?}");
+        let bytes = res.into_body().collect().await.unwrap().to_bytes();
+        assert_eq!(&bytes[..], body.as_bytes(), "{accept:?}");
+    }
+    let res = AllRoutes::routes()
+        .oneshot(make_request("/todos", "POST"))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::METHOD_NOT_ALLOWED);
+    assert_eq!(res.headers().get("allow").unwrap(), "GET,HEAD");
+}
+
+#[tokio::test]
+async fn should_choose_handlers_by_content_type() {
+    #[derive(Deserialize)]
+    struct TodosPath;
+    async fn create_from_form(_: TodosPath) -> &'static str {
+        "form"
+    }
+    async fn create_from_json(_: TodosPath) -> &'static str {
+        "json"
+    }
+    async fn upload(_: TodosPath) -> &'static str {
+        "multipart"
+    }
+    async fn todos_json(_: TodosPath) -> &'static str {
+        "list"
+    }
+    routes! {
+        name => AllRoutes,
+        "/todos" => TodosPath {
+            get(accept => json) => todos_json,
+            post(form) => create_from_form,
+            post(json) => create_from_json,
+            post(multipart) => upload,
+        },
+    }
+    for (content_type, status, body) in [
+        (
+            Some("application/x-www-form-urlencoded"),
+            StatusCode::OK,
+            "form",
+        ),
+        (
+            Some("application/json; charset=utf-8"),
+            StatusCode::OK,
+            "json",
+        ),
+        (
+            Some("multipart/form-data; boundary=X"),
+            StatusCode::OK,
+            "multipart",
+        ),
+        (Some("text/plain"), StatusCode::UNSUPPORTED_MEDIA_TYPE, ""),
+        (None, StatusCode::UNSUPPORTED_MEDIA_TYPE, ""),
+    ] {
+        let mut req = make_request("/todos", "POST");
+        if let Some(content_type) = content_type {
+            req.headers_mut()
+                .insert("content-type", HeaderValue::from_static(content_type));
+        }
+        let res = AllRoutes::routes().oneshot(req).await.unwrap();
+        assert_eq!(res.status(), status, "{content_type:?}");
+        assert!(res.headers().get("vary").is_none());
+        let bytes = res.into_body().collect().await.unwrap().to_bytes();
+        assert_eq!(&bytes[..], body.as_bytes(), "{content_type:?}");
+    }
+    let res = AllRoutes::routes()
+        .oneshot(make_request("/todos", "GET"))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(res.headers().get("vary").unwrap(), "accept");
+}
+
+#[tokio::test]
+async fn should_choose_handlers_by_content_type_and_accept() {
+    #[derive(Deserialize)]
+    struct ImportsPath;
+    async fn import_form(_: ImportsPath) -> &'static str {
+        "form"
+    }
+    async fn import_json(_: ImportsPath) -> &'static str {
+        "json"
+    }
+    async fn import_page(_: ImportsPath) -> &'static str {
+        "page"
+    }
+    routes! {
+        name => AllRoutes,
+        "/imports" => ImportsPath {
+            put(content_type => form) => import_form,
+            put(content_type => json, accept => json) => import_json,
+            put(content_type => json, accept => html) => import_page,
+        },
+    }
+    for (content_type, accept, status, vary, body) in [
+        (
+            "application/x-www-form-urlencoded",
+            None,
+            StatusCode::OK,
+            None,
+            "form",
+        ),
+        (
+            "application/x-www-form-urlencoded",
+            Some("text/html"),
+            StatusCode::OK,
+            None,
+            "form",
+        ),
+        (
+            "application/json",
+            None,
+            StatusCode::OK,
+            Some("accept"),
+            "json",
+        ),
+        (
+            "application/json",
+            Some("text/html"),
+            StatusCode::OK,
+            Some("accept"),
+            "page",
+        ),
+        (
+            "application/json",
+            Some("image/png"),
+            StatusCode::NOT_ACCEPTABLE,
+            Some("accept"),
+            "",
+        ),
+        (
+            "text/plain",
+            Some("text/html"),
+            StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            None,
+            "",
+        ),
+    ] {
+        let mut req = make_request("/imports", "PUT");
+        req.headers_mut()
+            .insert("content-type", HeaderValue::from_static(content_type));
+        if let Some(accept) = accept {
+            req.headers_mut()
+                .insert("accept", HeaderValue::from_static(accept));
+        }
+        let res = AllRoutes::routes().oneshot(req).await.unwrap();
+        assert_eq!(res.status(), status, "{content_type} {accept:?}");
+        assert_eq!(
+            res.headers().get("vary").map(|vary| vary.to_str().unwrap()),
+            vary,
+            "{content_type} {accept:?}"
+        );
+        let bytes = res.into_body().collect().await.unwrap().to_bytes();
+        assert_eq!(&bytes[..], body.as_bytes(), "{content_type} {accept:?}");
+    }
+}
+
+#[tokio::test]
+async fn should_require_headers() {
+    #[derive(Deserialize)]
+    struct TodoPath {
+        id: u32,
+    }
+    #[derive(Deserialize)]
+    struct ReportPath;
+    async fn todo_fragment(todo_path: TodoPath) -> String {
+        format!("fragment {}", todo_path.id)
+    }
+    async fn report(_: ReportPath) -> &'static str {
+        "report"
+    }
+    async fn not_htmx() -> (StatusCode, &'static str) {
+        (StatusCode::NOT_FOUND, "htmx only")
+    }
+    routes! {
+        name => AllRoutes,
+        "/fragments" => group {
+            htmx_only => not_htmx,
+            "/todos/{id: u32}" => TodoPath {
+                get => todo_fragment,
+            },
+            "/empty" => status 204,
+        },
+        "/report" => ReportPath {
+            get => report,
+            require_header "X-Api-Key",
+        },
+    }
+    for (path, header, status, body) in [
+        (
+            "/fragments/todos/1",
+            Some(("hx-request", "true")),
+            StatusCode::OK,
+            "fragment 1",
+        ),
+        (
+            "/fragments/todos/1",
+            None,
+            StatusCode::NOT_FOUND,
+            "htmx only",
+        ),
+        (
+            "/fragments/todos/x",
+            None,
+            StatusCode::NOT_FOUND,
+            "htmx only",
+        ),
+        (
+            "/fragments/empty",
+            Some(("hx-request", "true")),
+            StatusCode::NO_CONTENT,
+            "",
+        ),
+        ("/fragments/empty", None, StatusCode::NOT_FOUND, "htmx only"),
+        (
+            "/report",
+            Some(("x-api-key", "secret")),
+            StatusCode::OK,
+            "report",
+        ),
+        ("/report", None, StatusCode::BAD_REQUEST, ""),
+    ] {
+        let mut req = make_request(path, "GET");
+        if let Some((name, value)) = header {
+            req.headers_mut()
+                .insert(name, HeaderValue::from_static(value));
+        }
+        let res = AllRoutes::routes().oneshot(req).await.unwrap();
+        assert_eq!(res.status(), status, "{path} {header:?}");
+        assert!(res.headers().contains_key("vary"), "{path} {header:?}");
+        let bytes = res.into_body().collect().await.unwrap().to_bytes();
+        assert_eq!(&bytes[..], body.as_bytes(), "{path} {header:?}");
+    }
+}
+
+#[tokio::test]
+async fn should_fall_through_failed_guards() {
+    #[derive(Clone)]
+    struct AppState {
+        beta: bool,
+    }
+    #[derive(Deserialize)]
+    struct DashboardPath;
+    #[derive(Deserialize)]
+    struct BetaPath;
+    #[derive(Deserialize)]
+    struct LocalPath;
+    #[derive(Deserialize)]
+    struct NestedBetaPath;
+    async fn admin_dashboard(_: DashboardPath) -> &'static str {
+        "admin"
+    }
+    async fn user_dashboard(_: DashboardPath) -> &'static str {
+        "user"
+    }
+    async fn beta(_: BetaPath) -> &'static str {
+        "beta"
+    }
+    async fn local(_: LocalPath) -> &'static str {
+        "local"
+    }
+    async fn nested_beta(_: NestedBetaPath) -> &'static str {
+        "beta"
+    }
+    async fn fallback() -> (StatusCode, &'static str) {
+        (StatusCode::NOT_FOUND, "fallback")
+    }
+    fn is_admin(parts: &Parts, _: &AppState) -> bool {
+        parts.uri.query() == Some("admin")
+    }
+    async fn beta_enabled(_: &Parts, state: &AppState) -> bool {
+        state.beta
+    }
+    fn is_local(parts: &Parts, _: &AppState) -> bool {
+        parts.headers.get("x-forwarded-for").is_none()
+    }
+    routes! {
+        name => AllRoutes,
+        state => AppState,
+        fallback => fallback,
+        "/dashboard" => DashboardPath {
+            get(guard => is_admin) => admin_dashboard,
+            get => user_dashboard,
+        },
+        "/beta" => group {
+            guard => beta_enabled,
+            "/" => BetaPath {
+                get => beta
+            },
+        },
+        "/local" => LocalPath {
+            get => local,
+            guard => is_local,
+        },
+    }
+    for (beta, path, header, status, body) in [
+        (false, "/dashboard?admin", None, StatusCode::OK, "admin"),
+        (false, "/dashboard", None, StatusCode::OK, "user"),
+        (true, "/beta", None, StatusCode::OK, "beta"),
+        (false, "/beta", None, StatusCode::NOT_FOUND, "fallback"),
+        (false, "/local", None, StatusCode::OK, "local"),
+        (
+            false,
+            "/local",
+            Some("10.0.0.1"),
+            StatusCode::NOT_FOUND,
+            "fallback",
+        ),
+    ] {
+        let mut req = make_request(path, "GET");
+        if let Some(header) = header {
+            req.headers_mut()
+                .insert("x-forwarded-for", HeaderValue::from_static(header));
+        }
+        let res = AllRoutes::routes()
+            .with_state(AppState { beta })
+            .oneshot(req)
+            .await
+            .unwrap();
+        assert_eq!(res.status(), status, "{path} {beta}");
+        let bytes = res.into_body().collect().await.unwrap().to_bytes();
+        assert_eq!(&bytes[..], body.as_bytes(), "{path} {beta}");
+    }
+    routes! {
+        name => NestedRoutes,
+        state => AppState,
+        "/beta" => NestedBetaPath {
+            get => nested_beta,
+            guard => beta_enabled,
+        },
+    }
+    routes! {
+        name => OuterRoutes,
+        state => AppState,
+        fallback => fallback,
+        "/v1" => nest NestedRoutes,
+    }
+    routes! {
+        name => MergedRoutes,
+        state => AppState,
+        fallback => fallback,
+        merge => NestedRoutes,
+    }
+    for (path, body) in [("/v1/beta", "fallback"), ("/v1/missing", "fallback")] {
+        let res = OuterRoutes::routes()
+            .with_state(AppState { beta: false })
+            .oneshot(make_request(path, "GET"))
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::NOT_FOUND, "{path}");
+        let bytes = res.into_body().collect().await.unwrap().to_bytes();
+        assert_eq!(&bytes[..], body.as_bytes(), "{path}");
+    }
+    let res = MergedRoutes::routes()
+        .with_state(AppState { beta: false })
+        .oneshot(make_request("/beta", "GET"))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::NOT_FOUND);
+    let bytes = res.into_body().collect().await.unwrap().to_bytes();
+    assert_eq!(&bytes[..], b"fallback");
+}
+
+#[tokio::test]
+async fn should_route_controller_methods() {
+    #[derive(Deserialize)]
+    struct TodoPath {
+        id: usize,
+    }
+    #[derive(Deserialize, better_routes::Path)]
+    #[typed_path("/todo")]
+    struct TodosPath;
+    struct Todos;
+    #[better_routes::controller(name => TodoRoutes, layer => tag("todos"))]
+    impl Todos {
+        #[get("/todo/:id")]
+        async fn get_todo(todo_path: TodoPath) -> String {
+            format!("todo {}", todo_path.id)
+        }
+
+        #[put("/todo/:id")]
+        #[patch("/todo/:id")]
+        async fn update_todo(todo_path: TodoPath) -> String {
+            format!("updated todo {}", todo_path.id)
+        }
+
+        #[post]
+        async fn create_todo(_: TodosPath) -> &'static str {
+            "created"
+        }
+
+        #[allow(dead_code)]
+        fn helper() {}
+    }
+    assert_eq!(<TodoPath as TypedPath>::PATH, "/todo/:id");
+    let router = TodoRoutes::routes();
+    for (path, method, expected) in [
+        ("/todo/7", "GET", &b"todo 7"[..]),
+        ("/todo/7", "PUT", b"updated todo 7"),
+        ("/todo/7", "PATCH", b"updated todo 7"),
+        ("/todo", "POST", b"created"),
+    ] {
+        let res = router
+            .clone()
+            .oneshot(make_request(path, method))
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::OK, "{method} {path}");
+        assert_eq!(res.headers()["x-layer"], "todos");
+        let body = res.into_body().collect().await.unwrap().to_bytes();
+        assert_eq!(&body[..], expected, "{method} {path}");
+    }
+    let delete_res = router
+        .clone()
+        .oneshot(make_request("/todo/7", "DELETE"))
+        .await
+        .unwrap();
+    assert_eq!(delete_res.status(), StatusCode::METHOD_NOT_ALLOWED);
+    assert_eq!(delete_res.headers()["allow"], "GET,HEAD,PUT,PATCH");
+}
+
+#[tokio::test]
+async fn should_route_registered_handlers() {
+    #[derive(Deserialize)]
+    struct HomePath;
+    async fn home(_: HomePath) -> &'static str {
+        "home"
+    }
+    #[derive(Deserialize, better_routes::Path)]
+    #[typed_path("/user/:id")]
+    struct UserPath {
+        id: usize,
+    }
+    #[better_routes::handler(AllRoutes, get)]
+    async fn user(user_path: UserPath) -> String {
+        format!("user {}", user_path.id)
+    }
+    #[better_routes::handler(AllRoutes, put, patch)]
+    async fn update_user(user_path: UserPath) -> String {
+        format!("updated user {}", user_path.id)
+    }
+    routes! {
+        name => AllRoutes,
+        layer => tag("all"),
+        "/" => HomePath {
+            get => home,
+        },
+    }
+    let router = AllRoutes::routes();
+    for (path, method, expected) in [
+        ("/", "GET", &b"home"[..]),
+        ("/user/7", "GET", b"user 7"),
+        ("/user/7", "PUT", b"updated user 7"),
+        ("/user/7", "PATCH", b"updated user 7"),
+    ] {
+        let res = router
+            .clone()
+            .oneshot(make_request(path, method))
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::OK, "{method} {path}");
+        assert_eq!(res.headers()["x-layer"], "all");
+        let body = res.into_body().collect().await.unwrap().to_bytes();
+        assert_eq!(&body[..], expected, "{method} {path}");
+    }
+}
+
+#[tokio::test]
+async fn should_route_layer_registered_handlers() {
+    #[derive(Deserialize, better_routes::Path)]
+    #[typed_path("/user/:id")]
+    struct UserPath {
+        id: usize,
+    }
+    #[better_routes::handler(AllRoutes, get)]
+    async fn user(user_path: UserPath) -> String {
+        format!("user {}", user_path.id)
+    }
+    routes! {
+        name => AllRoutes,
+        route_layer => tag("route"),
+    }
+    routes! {
+        name => EmptyRoutes,
+        route_layer => tag("route"),
+    }
+    let res = AllRoutes::routes()
+        .oneshot(make_request("/user/7", "GET"))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(res.headers()["x-layer"], "route");
+    let res = EmptyRoutes::routes()
+        .oneshot(make_request("/user/7", "GET"))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::NOT_FOUND);
+}
+
+#[test]
+#[should_panic(expected = "duplicate get handler for `/user/:id`")]
+fn should_panic_on_duplicate_registered_handlers() {
+    #[derive(Deserialize, better_routes::Path)]
+    #[typed_path("/user/:id")]
+    struct UserPath {
+        #[allow(dead_code)]
+        id: usize,
+    }
+    #[better_routes::handler(AllRoutes, get)]
+    async fn user(_: UserPath) {}
+    #[better_routes::handler(AllRoutes, get)]
+    async fn other_user(_: UserPath) {}
+    routes! {
+        name => AllRoutes,
+    }
+    let _ = AllRoutes::routes();
+}
@@ -0,0 +1,14 @@
+use better_routes::routes;
+use serde::Deserialize;
+#[derive(Deserialize)]
+struct Foo {
+    id: u64,
+}
+async fn get(_: Foo) {}
+routes! {
+    name => AllRoutes,
+    "/foo/:id" | "/f/:name" => Foo {
+        get => get
+    }
+}
+fn main() {}
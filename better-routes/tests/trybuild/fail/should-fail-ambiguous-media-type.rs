@@ -0,0 +1,12 @@
+use better_routes::routes;
+use serde::Deserialize;
+#[derive(Deserialize)]
+struct Foo;
+async fn json(_: Foo) {}
+routes! {
+    name => AllRoutes,
+    "/foo" => Foo {
+        [get, post](json) => json,
+    }
+}
+fn main() {}
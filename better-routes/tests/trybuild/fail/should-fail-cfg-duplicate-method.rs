@@ -0,0 +1,14 @@
+use better_routes::routes;
+use serde::Deserialize;
+#[derive(Deserialize)]
+struct Foo;
+async fn get(_: Foo) {}
+routes! {
+    name => AllRoutes,
+    "/" => Foo {
+        get => get,
+        #[cfg(all())]
+        get => get,
+    }
+}
+fn main() {}
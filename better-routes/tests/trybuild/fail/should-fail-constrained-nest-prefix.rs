@@ -0,0 +1,16 @@
+use better_routes::routes;
+use serde::Deserialize;
+#[derive(Deserialize)]
+struct Foo;
+async fn get(_: Foo) {}
+routes! {
+    name => InnerRoutes,
+    "/x" => Foo {
+        get => get
+    }
+}
+routes! {
+    name => AllRoutes,
+    "/v/:id<\\d+>" => nest InnerRoutes,
+}
+fn main() {}
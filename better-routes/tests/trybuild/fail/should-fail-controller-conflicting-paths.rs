@@ -0,0 +1,14 @@
+use better_routes::controller;
+use serde::Deserialize;
+#[derive(Deserialize)]
+struct Foo;
+struct Handlers;
+#[controller(name => AllRoutes)]
+impl Handlers {
+    #[get("/foo")]
+    async fn get(_: Foo) {}
+
+    #[post("/bar")]
+    async fn post(_: Foo) {}
+}
+fn main() {}
@@ -0,0 +1,13 @@
+use better_routes::routes;
+use serde::Deserialize;
+#[derive(Deserialize)]
+struct Foo;
+async fn get(_: Foo) {}
+routes! {
+    name => AllRoutes,
+    #[deprecated]
+    "/foo" => Foo {
+        get => get
+    }
+}
+fn main() {}
@@ -0,0 +1,15 @@
+use better_routes::{routes, Path};
+use serde::Deserialize;
+#[derive(Deserialize, Path)]
+#[typed_path("/foo")]
+struct Foo;
+async fn get(_: Foo) {}
+routes! {
+    name => AllRoutes,
+    "/api" => group {
+        Foo {
+            get => get
+        }
+    }
+}
+fn main() {}
@@ -0,0 +1,13 @@
+use better_routes::routes;
+use serde::Deserialize;
+#[derive(Deserialize)]
+struct Foo;
+async fn get(_: Foo) {}
+routes! {
+    name => AllRoutes,
+    "/" => Foo {
+        [get, head] => get,
+        get => get,
+    }
+}
+fn main() {}
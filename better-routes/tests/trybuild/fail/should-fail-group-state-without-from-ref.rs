@@ -0,0 +1,21 @@
+use axum::extract::State;
+use better_routes::routes;
+use serde::Deserialize;
+#[derive(Clone)]
+struct AppState;
+#[derive(Clone)]
+struct DbState;
+#[derive(Deserialize)]
+struct Foo;
+async fn get(_: Foo, _: State<DbState>) {}
+routes! {
+    name => AllRoutes,
+    state => AppState,
+    "/api" => group {
+        state => DbState,
+        "/foo" => Foo {
+            get => get
+        }
+    }
+}
+fn main() {}
@@ -0,0 +1,18 @@
+use axum::http::request::Parts;
+use better_routes::routes;
+use serde::Deserialize;
+#[derive(Deserialize)]
+struct Foo;
+async fn foo(_: Foo) {}
+async fn admin(_: Foo) {}
+fn is_admin(_: &Parts, _: &()) -> bool {
+    true
+}
+routes! {
+    name => AllRoutes,
+    "/foo" => Foo {
+        get => foo,
+        get(guard => is_admin) => admin,
+    }
+}
+fn main() {}
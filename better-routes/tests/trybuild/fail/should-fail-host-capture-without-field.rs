@@ -0,0 +1,16 @@
+use better_routes::routes;
+use serde::Deserialize;
+#[derive(Deserialize)]
+struct Foo {
+    id: u64,
+}
+async fn get(_: Foo) {}
+routes! {
+    name => AllRoutes,
+    host ":tenant.example.com" => {
+        "/foo/:id" => Foo {
+            get => get
+        }
+    }
+}
+fn main() {}
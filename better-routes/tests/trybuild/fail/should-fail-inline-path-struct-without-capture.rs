@@ -0,0 +1,9 @@
+use better_routes::routes;
+async fn get(_: Foo) {}
+routes! {
+    name => AllRoutes,
+    "/foo/:id" => Foo { id: u64, name: String } {
+        get => get
+    }
+}
+fn main() {}
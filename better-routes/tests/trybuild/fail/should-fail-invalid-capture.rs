@@ -1,4 +1,3 @@
-use axum_extra::routing::RouterExt;
 use better_routes::routes;
 use serde::Deserialize;
 #[derive(Deserialize)]
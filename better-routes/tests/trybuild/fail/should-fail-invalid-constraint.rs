@@ -0,0 +1,14 @@
+use better_routes::routes;
+use serde::Deserialize;
+#[derive(Deserialize)]
+struct Foo {
+    id: u64,
+}
+async fn get(_: Foo) {}
+routes! {
+    name => AllRoutes,
+    "/foo/:id<[0-9>" => Foo {
+        get => get
+    }
+}
+fn main() {}
@@ -0,0 +1,18 @@
+use better_routes::routes;
+use serde::Deserialize;
+#[derive(Deserialize)]
+struct Foo;
+async fn foo(_: Foo) {}
+routes! {
+    name => InnerRoutes,
+    "/foo" => Foo {
+        get => foo
+    }
+}
+routes! {
+    name => AllRoutes,
+    "/api" => group {
+        merge => InnerRoutes,
+    }
+}
+fn main() {}
@@ -0,0 +1,19 @@
+use better_routes::routes;
+use serde::Deserialize;
+#[derive(Clone)]
+struct AppState;
+#[derive(Deserialize)]
+struct Foo;
+async fn get(_: Foo) {}
+routes! {
+    name => OtherRoutes,
+    "/foo" => Foo {
+        get => get
+    }
+}
+routes! {
+    name => AllRoutes,
+    state => AppState,
+    merge => OtherRoutes,
+}
+fn main() {}
@@ -0,0 +1,14 @@
+use better_routes::routes;
+use serde::Deserialize;
+#[derive(Deserialize)]
+struct Foo;
+async fn any(_: Foo) {}
+async fn not_allowed(_: Foo) {}
+routes! {
+    name => AllRoutes,
+    "/" => Foo {
+        any => any,
+        method_not_allowed => not_allowed,
+    }
+}
+fn main() {}
@@ -0,0 +1,14 @@
+use better_routes::routes;
+use serde::Deserialize;
+#[derive(Deserialize)]
+struct Foo;
+async fn create(_: Foo) {}
+async fn create_json(_: Foo) {}
+routes! {
+    name => AllRoutes,
+    "/foo" => Foo {
+        post(content_type => json) => create,
+        post(content_type => json, accept => json) => create_json,
+    }
+}
+fn main() {}
@@ -0,0 +1,14 @@
+use better_routes::routes;
+use serde::Deserialize;
+#[derive(Deserialize)]
+struct Foo;
+async fn page(_: Foo) {}
+async fn json(_: Foo) {}
+routes! {
+    name => AllRoutes,
+    "/foo" => Foo {
+        get => page,
+        get(accept => json) => json,
+    }
+}
+fn main() {}
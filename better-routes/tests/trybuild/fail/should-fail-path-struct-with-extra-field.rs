@@ -0,0 +1,15 @@
+use better_routes::routes;
+use serde::Deserialize;
+#[derive(Deserialize)]
+struct P {
+    id: u32,
+    extra: u32,
+}
+async fn get(_: P) {}
+routes! {
+    name => AllRoutes,
+    "/p/:id" => P {
+        get => get
+    }
+}
+fn main() {}
@@ -0,0 +1,20 @@
+use better_routes::routes;
+use serde::Deserialize;
+#[derive(Deserialize)]
+struct Foo;
+#[derive(Deserialize)]
+struct Bar;
+async fn foo(_: Foo) {}
+async fn bar(_: Bar) {}
+routes! {
+    name => AllRoutes,
+    host "api.example.com" => {
+        "/foo" => Foo {
+            get => foo
+        },
+    },
+    "/foo" => Bar {
+        post => bar
+    },
+}
+fn main() {}
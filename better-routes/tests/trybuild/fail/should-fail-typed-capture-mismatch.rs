@@ -0,0 +1,14 @@
+use better_routes::routes;
+use serde::Deserialize;
+#[derive(Deserialize)]
+struct Foo {
+    id: String,
+}
+async fn get(_: Foo) {}
+routes! {
+    name => AllRoutes,
+    "/foo/{id: u64}" => Foo {
+        get => get
+    }
+}
+fn main() {}
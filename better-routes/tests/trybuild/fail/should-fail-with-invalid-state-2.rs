@@ -1,5 +1,4 @@
 use axum::extract::State;
-use axum_extra::routing::RouterExt;
 use better_routes::routes;
 use serde::Deserialize;
 #[derive(Clone)]
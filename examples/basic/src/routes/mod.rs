@@ -1,7 +1,6 @@
 use axum::extract::rejection::PathRejection;
 use axum::http::StatusCode;
 use axum::response::{IntoResponse, Response};
-use axum_extra::routing::RouterExt;
 use better_routes::routes;
 
 use crate::AppState;
@@ -33,11 +32,15 @@ routes! {
     "/" => Home{
         get => index
     },
-    "/api/todo" => Todo {
-        post => create_todo
-    },
-    "/api/todo/:id" => TodoWithId {
-        put => update_todo,
-        delete => delete_todo,
+    "/api" => group {
+        // Reject non-HTMX requests for api routes
+        htmx_only,
+        "/todo" => Todo {
+            post => create_todo
+        },
+        "/todo/:id" => TodoWithId {
+            put => update_todo,
+            delete => delete_todo,
+        },
     },
 }